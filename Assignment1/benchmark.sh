@@ -0,0 +1,11 @@
+#!/bin/bash
+
+source envconf
+
+cd benchmark
+
+# Build binary
+cargo build --release
+
+# Run the benchmark
+target/release/benchmark
@@ -0,0 +1,16 @@
+extern crate genetic;
+
+use genetic::benchmark;
+use genetic::config::Config;
+
+fn main() {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => panic!("{}", err),
+    };
+    let report = benchmark::run_benchmark(&config);
+    println!("{}", report.to_markdown());
+    if !config.bench_output_path.is_empty() {
+        report.write_to_path(&config.bench_output_path);
+    }
+}
@@ -0,0 +1,247 @@
+//! Compares the packed u32 genes and bitset crossover with the previous
+//! enum genes and HashSet crossover on a population of 1000 chromosomes.
+
+use std::collections::HashSet;
+use std::mem;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use genetic::config::Config;
+use genetic::generator::InstanceGenerator;
+use genetic::parser;
+use genetic::problem::{Model, Problem};
+use genetic::simulation::{Chromosome, Encode};
+use genetic::solution::Solution;
+
+const POPULATION_SIZE: usize = 1000;
+
+#[derive(Clone, Eq, Hash, PartialEq)]
+enum LegacyGene {
+    Customer(i32),
+    Depot(i32),
+}
+
+impl LegacyGene {
+    fn value(&self) -> i32 {
+        match self {
+            LegacyGene::Customer(val) => *val,
+            LegacyGene::Depot(val) => *val,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct LegacyChromosome {
+    genes: Vec<LegacyGene>,
+}
+
+impl LegacyChromosome {
+    fn new(chromosome: &Chromosome) -> LegacyChromosome {
+        let genes = chromosome
+            .genes
+            .iter()
+            .map(|gene| {
+                if gene.is_depot() {
+                    LegacyGene::Depot(gene.value())
+                } else {
+                    LegacyGene::Customer(gene.value())
+                }
+            })
+            .collect();
+        LegacyChromosome { genes }
+    }
+
+    fn order_one_crossover(
+        &self,
+        other: &LegacyChromosome,
+    ) -> (LegacyChromosome, LegacyChromosome) {
+        let mut child_one = self.clone();
+        let mut child_two = other.clone();
+
+        let chromosome_length = self.genes.len();
+        let mut rng = rand::thread_rng();
+        let index_one = rng.gen_range(0, chromosome_length);
+        let index_two = rng.gen_range(index_one, chromosome_length);
+
+        let set_one: HashSet<&LegacyGene> = self.genes[index_one..index_two].iter().collect();
+        let set_two: HashSet<&LegacyGene> = other.genes[index_one..index_two].iter().collect();
+
+        let mut insert_index_one = index_two;
+        let mut insert_index_two = index_two;
+
+        for i in 0..chromosome_length {
+            let new_index = (index_two + i) % chromosome_length;
+            let new_gene_one = &other.genes[new_index];
+            if !set_one.contains(&new_gene_one) {
+                child_one.genes[insert_index_one] = new_gene_one.clone();
+                insert_index_one = (insert_index_one + 1) % chromosome_length;
+            }
+
+            let new_gene_two = &self.genes[new_index];
+            if !set_two.contains(&new_gene_two) {
+                child_two.genes[insert_index_two] = new_gene_two.clone();
+                insert_index_two = (insert_index_two + 1) % chromosome_length;
+            }
+        }
+
+        (child_one, child_two)
+    }
+
+    fn single_swap_mutation(&self) -> LegacyChromosome {
+        let mut new_chromosome = self.clone();
+        let customers: Vec<usize> = (0..self.genes.len())
+            .filter(|i| matches!(self.genes[*i], LegacyGene::Customer(_)))
+            .collect();
+        let mut rng = rand::thread_rng();
+        let index_one = customers[rng.gen_range(0, customers.len())];
+        let index_two = customers[rng.gen_range(0, customers.len())];
+        new_chromosome.genes.swap(index_one, index_two);
+        new_chromosome
+    }
+
+    fn evaluate(&self, model: &Model) -> f64 {
+        let total_genes = self.genes.len();
+        let start_index = self
+            .genes
+            .iter()
+            .position(|gene| matches!(gene, LegacyGene::Depot(_)))
+            .unwrap();
+
+        let mut score: f64 = 0.0;
+        let mut index = start_index;
+        let mut current_node = self.genes[index].value();
+        let mut vehicle_node = current_node;
+        let mut distance: f64;
+        let mut capacity_left = model.get_demand(vehicle_node as usize);
+
+        loop {
+            index = (index + 1) % total_genes;
+            match self.genes[index] {
+                LegacyGene::Depot(node) => {
+                    distance = model.get_distance(current_node as usize, vehicle_node as usize);
+                    if current_node != vehicle_node {
+                        score += model.vehicle_cost;
+                    }
+                    current_node = node;
+                    vehicle_node = node;
+                    capacity_left = model.get_demand(vehicle_node as usize);
+                }
+                LegacyGene::Customer(node) => {
+                    distance = model.get_distance(current_node as usize, node as usize);
+                    current_node = node;
+                    capacity_left -= model.get_demand(current_node as usize);
+                }
+            }
+            score += distance;
+            if capacity_left < 0 {
+                score += model.capacity_penalty;
+            }
+            if index == start_index {
+                break;
+            }
+        }
+        score
+    }
+}
+
+fn generate_population(problem: &Problem) -> Vec<Chromosome> {
+    let mut rng = StdRng::seed_from_u64(1);
+    (0..POPULATION_SIZE)
+        .map(|_| {
+            let mut customers: Vec<i32> = (1..=problem.num_customers).collect();
+            customers.shuffle(&mut rng);
+            let mut routes: Vec<Vec<i32>> =
+                problem.vehicles.iter().map(|v| vec![v.number]).collect();
+            for customer in customers {
+                let index = rng.gen_range(0, routes.len());
+                routes[index].push(customer);
+            }
+            for route in routes.iter_mut() {
+                route.push(route[0]);
+            }
+            Solution::new(routes).encode()
+        })
+        .collect()
+}
+
+fn bench_chromosomes(c: &mut Criterion) {
+    let config = Config::default();
+    let generator = InstanceGenerator::new(200, 4, 1);
+    let problem = Problem::from_lines(&parser::load_str(&generator.generate()), &config);
+    let model = problem.get_model();
+
+    let population = generate_population(&problem);
+    let legacy: Vec<LegacyChromosome> = population.iter().map(LegacyChromosome::new).collect();
+
+    let genes = population[0].genes.len() * POPULATION_SIZE;
+    println!(
+        "Gene memory for {} chromosomes: packed {} KiB, legacy {} KiB",
+        POPULATION_SIZE,
+        genes * mem::size_of_val(&population[0].genes[0]) / 1024,
+        genes * mem::size_of::<LegacyGene>() / 1024
+    );
+
+    let mut group = c.benchmark_group("crossover");
+    group.bench_function("packed", |b| {
+        b.iter(|| {
+            population
+                .chunks(2)
+                .map(|pair| pair[0].order_one_crossover(&pair[1]))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            legacy
+                .chunks(2)
+                .map(|pair| pair[0].order_one_crossover(&pair[1]))
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("evaluate");
+    group.bench_function("packed", |b| {
+        let mut population = population.clone();
+        b.iter(|| {
+            population
+                .iter_mut()
+                .map(|chromosome| chromosome.evaluate(model))
+                .sum::<f64>()
+        })
+    });
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            legacy
+                .iter()
+                .map(|chromosome| chromosome.evaluate(model))
+                .sum::<f64>()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("single_swap_mutation");
+    group.bench_function("packed", |b| {
+        b.iter(|| {
+            population
+                .iter()
+                .map(|chromosome| chromosome.single_swap_mutation())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            legacy
+                .iter()
+                .map(|chromosome| chromosome.single_swap_mutation())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_chromosomes);
+criterion_main!(benches);
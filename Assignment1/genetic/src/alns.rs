@@ -0,0 +1,273 @@
+use std::f64;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::config::Config;
+use crate::plan::RoutePlan;
+use crate::problem::{CustomerChange, Model};
+use crate::random;
+use crate::simulation::Chromosome;
+use crate::solution::Solution;
+use crate::solver::{self, SearchState, Solver};
+
+// Operator scores for a new best, an improving and an accepted solution
+const SCORE_BEST: f64 = 33.0;
+const SCORE_IMPROVED: f64 = 9.0;
+const SCORE_ACCEPTED: f64 = 13.0;
+
+#[derive(Clone, Copy, Debug)]
+enum Destroy {
+    Random,
+    Worst,
+    Shaw,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Repair {
+    Greedy,
+    Regret,
+}
+
+const DESTROY_OPERATORS: [Destroy; 3] = [Destroy::Random, Destroy::Worst, Destroy::Shaw];
+const REPAIR_OPERATORS: [Repair; 2] = [Repair::Greedy, Repair::Regret];
+
+/// Adaptive weights of a set of operators, updated every segment
+struct OperatorWeights {
+    weights: Vec<f64>,
+    scores: Vec<f64>,
+    uses: Vec<usize>,
+}
+
+impl OperatorWeights {
+    fn new(count: usize) -> OperatorWeights {
+        OperatorWeights {
+            weights: vec![1.0; count],
+            scores: vec![0.0; count],
+            uses: vec![0; count],
+        }
+    }
+
+    /// Roulette wheel selection on the weights
+    fn select<R: Rng>(&self, rng: &mut R) -> usize {
+        let total: f64 = self.weights.iter().sum();
+        let mut value = rng.gen::<f64>() * total;
+        for (i, weight) in self.weights.iter().enumerate() {
+            value -= weight;
+            if value <= 0.0 {
+                return i;
+            }
+        }
+        self.weights.len() - 1
+    }
+
+    fn reward(&mut self, operator: usize, score: f64) {
+        self.uses[operator] += 1;
+        self.scores[operator] += score;
+    }
+
+    fn update(&mut self, reaction: f64) {
+        for i in 0..self.weights.len() {
+            if self.uses[i] > 0 {
+                let performance = self.scores[i] / self.uses[i] as f64;
+                self.weights[i] = (1.0 - reaction) * self.weights[i] + reaction * performance;
+            }
+            self.weights[i] = self.weights[i].max(0.01);
+            self.scores[i] = 0.0;
+            self.uses[i] = 0;
+        }
+    }
+}
+
+/// Adaptive large neighbourhood search with simulated annealing acceptance
+pub struct Alns {
+    state: Option<SearchState>,
+    temperature: f64,
+    destroy_weights: OperatorWeights,
+    repair_weights: OperatorWeights,
+}
+
+impl Alns {
+    pub fn new(config: &Config) -> Alns {
+        Alns {
+            state: None,
+            temperature: config.annealing_temperature,
+            destroy_weights: OperatorWeights::new(DESTROY_OPERATORS.len()),
+            repair_weights: OperatorWeights::new(REPAIR_OPERATORS.len()),
+        }
+    }
+}
+
+/// Removes the customers from the plan and returns them
+fn destroy<R: Rng>(
+    operator: Destroy,
+    plan: &mut RoutePlan,
+    count: usize,
+    model: &Model,
+    rng: &mut R,
+) -> Vec<i32> {
+    let mut customers = plan.customers();
+    let removed: Vec<i32> = match operator {
+        Destroy::Random => {
+            customers.shuffle(rng);
+            customers.into_iter().take(count).collect()
+        }
+        // Customers that save the most distance when removed
+        Destroy::Worst => {
+            let mut savings: Vec<(i32, f64)> = customers
+                .iter()
+                .map(|customer| {
+                    let (route, position) = plan.find(*customer).unwrap();
+                    let mut without = plan.clone();
+                    without.routes[route].remove(position);
+                    let saving = plan.route_cost(route, model) - without.route_cost(route, model);
+                    (*customer, saving)
+                })
+                .collect();
+            savings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+            savings
+                .into_iter()
+                .take(count)
+                .map(|(customer, _)| customer)
+                .collect()
+        }
+        // Customers close to a random customer
+        Destroy::Shaw => {
+            let first = customers[rng.gen_range(0, customers.len())];
+            customers.sort_by(|a, b| {
+                let distance_a = model.get_distance(first as usize, *a as usize);
+                let distance_b = model.get_distance(first as usize, *b as usize);
+                distance_a.partial_cmp(&distance_b).unwrap()
+            });
+            customers.into_iter().take(count).collect()
+        }
+    };
+    for customer in removed.iter() {
+        plan.remove(*customer);
+    }
+    removed
+}
+
+/// Inserts the removed customers back into the plan
+fn repair<R: Rng>(
+    operator: Repair,
+    plan: &mut RoutePlan,
+    mut removed: Vec<i32>,
+    model: &Model,
+    rng: &mut R,
+) {
+    match operator {
+        Repair::Greedy => {
+            removed.shuffle(rng);
+            for customer in removed {
+                plan.insert_best(customer, model);
+            }
+        }
+        // Insert the customer with the highest regret-2 value first
+        Repair::Regret => {
+            while !removed.is_empty() {
+                let mut best: Option<(usize, f64)> = None;
+                let mut best_insertion = None;
+                for (i, customer) in removed.iter().enumerate() {
+                    let mut insertions = plan.route_insertions(*customer, model);
+                    insertions.sort_by(|a, b| a.cost.partial_cmp(&b.cost).unwrap());
+                    let regret = match insertions.get(1) {
+                        Some(second) => second.cost - insertions[0].cost,
+                        None => 0.0,
+                    };
+                    let is_better = match best {
+                        Some((_, best_regret)) => regret > best_regret,
+                        None => true,
+                    };
+                    if is_better {
+                        best = Some((i, regret));
+                        best_insertion = Some(insertions[0]);
+                    }
+                }
+                let (index, _) = best.unwrap();
+                let customer = removed.swap_remove(index);
+                plan.insert(&best_insertion.unwrap(), customer);
+            }
+        }
+    }
+    if model.has_pickups {
+        plan.repair_pairs(model);
+    }
+}
+
+impl Solver for Alns {
+    fn initial_size(&self, _config: &Config) -> usize {
+        1
+    }
+
+    fn seed(&mut self, chromosomes: Vec<Chromosome>, model: &Model) {
+        self.state = Some(SearchState::new(chromosomes, model));
+    }
+
+    fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        if let Some(state) = self.state.as_mut() {
+            state.update_customers(change, model);
+        }
+    }
+
+    fn run(&mut self, model: &Model, config: &Config) {
+        let state = solver::get_state_mut(&mut self.state);
+        let mut rng = random::rng();
+        let num_customers = state.current.customers().len();
+        if num_customers == 0 {
+            return;
+        }
+        let min_remove = ((num_customers as f64 * config.alns_remove_min) as usize).max(1);
+        let max_remove = ((num_customers as f64 * config.alns_remove_max) as usize).max(min_remove);
+
+        for _ in 0..config.solver_iterations {
+            let destroy_operator = self.destroy_weights.select(&mut rng);
+            let repair_operator = self.repair_weights.select(&mut rng);
+            let count = rng.gen_range(min_remove, max_remove + 1);
+
+            let mut plan = state.current.clone();
+            let removed = destroy(
+                DESTROY_OPERATORS[destroy_operator],
+                &mut plan,
+                count,
+                model,
+                &mut rng,
+            );
+            repair(
+                REPAIR_OPERATORS[repair_operator],
+                &mut plan,
+                removed,
+                model,
+                &mut rng,
+            );
+            let cost = plan.cost(model);
+            let delta = cost - state.current_cost;
+
+            let accept = delta <= 0.0 || rng.gen::<f64>() < (-delta / self.temperature).exp();
+            let score = if accept {
+                if state.accept(plan, cost, model) {
+                    SCORE_BEST
+                } else if delta < 0.0 {
+                    SCORE_IMPROVED
+                } else {
+                    SCORE_ACCEPTED
+                }
+            } else {
+                0.0
+            };
+            self.destroy_weights.reward(destroy_operator, score);
+            self.repair_weights.reward(repair_operator, score);
+
+            self.temperature = (self.temperature * config.annealing_cooling).max(f64::EPSILON);
+            state.iteration += 1;
+            if state.iteration % config.alns_segment == 0 {
+                self.destroy_weights.update(config.alns_reaction);
+                self.repair_weights.update(config.alns_reaction);
+            }
+        }
+    }
+
+    fn get_best_solution(&self) -> Solution {
+        solver::best_solution(&self.state)
+    }
+}
@@ -0,0 +1,115 @@
+use std::f64;
+
+use rand::Rng;
+
+use crate::config::Config;
+use crate::plan::RoutePlan;
+use crate::problem::{CustomerChange, Model};
+use crate::random;
+use crate::simulation::Chromosome;
+use crate::solution::Solution;
+use crate::solver::{self, SearchState, Solver};
+
+pub struct SimulatedAnnealing {
+    state: Option<SearchState>,
+    temperature: f64,
+}
+
+impl SimulatedAnnealing {
+    pub fn new(config: &Config) -> SimulatedAnnealing {
+        SimulatedAnnealing {
+            state: None,
+            temperature: config.annealing_temperature,
+        }
+    }
+}
+
+/// Applies a random relocate, swap or 2-opt move to the plan.
+/// Returns the routes that were changed.
+pub fn random_move<R: Rng>(plan: &mut RoutePlan, rng: &mut R) -> Vec<usize> {
+    let customers = plan.customers();
+    if customers.is_empty() {
+        return Vec::new();
+    }
+    match rng.gen_range(0, 3) {
+        // Relocate a customer to a random position
+        0 => {
+            let customer = customers[rng.gen_range(0, customers.len())];
+            let (from, _) = plan.remove(customer).unwrap();
+            let to = rng.gen_range(0, plan.routes.len());
+            let position = rng.gen_range(0, plan.routes[to].len() + 1);
+            plan.routes[to].insert(position, customer);
+            vec![from, to]
+        }
+        // Swap two customers
+        1 => {
+            let one = customers[rng.gen_range(0, customers.len())];
+            let two = customers[rng.gen_range(0, customers.len())];
+            let (route_one, position_one) = plan.find(one).unwrap();
+            let (route_two, position_two) = plan.find(two).unwrap();
+            plan.routes[route_one][position_one] = two;
+            plan.routes[route_two][position_two] = one;
+            vec![route_one, route_two]
+        }
+        // Reverse a segment of a route
+        _ => {
+            let customer = customers[rng.gen_range(0, customers.len())];
+            let (route, start) = plan.find(customer).unwrap();
+            let end = rng.gen_range(start, plan.routes[route].len());
+            plan.routes[route][start..=end].reverse();
+            vec![route]
+        }
+    }
+}
+
+/// Cost of the routes, counting each route once
+fn routes_cost(plan: &RoutePlan, routes: &[usize], model: &Model) -> f64 {
+    let mut cost = 0.0;
+    for (i, route) in routes.iter().enumerate() {
+        if !routes[..i].contains(route) {
+            cost += plan.route_cost(*route, model);
+        }
+    }
+    cost
+}
+
+impl Solver for SimulatedAnnealing {
+    fn initial_size(&self, _config: &Config) -> usize {
+        1
+    }
+
+    fn seed(&mut self, chromosomes: Vec<Chromosome>, model: &Model) {
+        self.state = Some(SearchState::new(chromosomes, model));
+    }
+
+    fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        if let Some(state) = self.state.as_mut() {
+            state.update_customers(change, model);
+        }
+    }
+
+    fn run(&mut self, model: &Model, config: &Config) {
+        let state = solver::get_state_mut(&mut self.state);
+        let mut rng = random::rng();
+
+        for _ in 0..config.solver_iterations {
+            let mut plan = state.current.clone();
+            let routes = random_move(&mut plan, &mut rng);
+            let delta =
+                routes_cost(&plan, &routes, model) - routes_cost(&state.current, &routes, model);
+
+            let accept = delta <= 0.0 || rng.gen::<f64>() < (-delta / self.temperature).exp();
+            if accept {
+                let cost = state.current_cost + delta;
+                state.accept(plan, cost, model);
+            }
+
+            self.temperature = (self.temperature * config.annealing_cooling).max(f64::EPSILON);
+            state.iteration += 1;
+        }
+    }
+
+    fn get_best_solution(&self) -> Solution {
+        solver::best_solution(&self.state)
+    }
+}
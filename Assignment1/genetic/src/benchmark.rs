@@ -0,0 +1,480 @@
+use std::f64;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::config::Config;
+use crate::problem::Problem;
+use crate::random;
+use crate::solution::OptimalSolution;
+
+// Significance level used to mark differences between two configs
+const ALPHA: f64 = 0.05;
+
+pub struct Instance {
+    pub name: String,
+    pub problem_path: String,
+    pub optimum: Option<f64>,
+}
+
+impl Instance {
+    /// The optimum is read from the .res file if it exists
+    pub fn new(problem_path: &str, optimal_solution_path: &str) -> Instance {
+        let name = match Path::new(problem_path).file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => problem_path.to_string(),
+        };
+        let optimum =
+            if !optimal_solution_path.is_empty() && Path::new(optimal_solution_path).is_file() {
+                Some(OptimalSolution::new(optimal_solution_path.to_string()).get_score())
+            } else {
+                None
+            };
+        Instance {
+            name,
+            problem_path: problem_path.to_string(),
+            optimum,
+        }
+    }
+
+    /// Score a run has to reach to count as a hit
+    pub fn get_target(&self, config: &Config) -> Option<f64> {
+        self.optimum
+            .map(|optimum| optimum * (1.0 + config.bench_target_gap))
+    }
+}
+
+pub struct RunResult {
+    pub seed: u64,
+    pub score: f64,
+    // Seconds
+    pub time: f64,
+    pub time_to_target: Option<f64>,
+}
+
+pub struct Statistics {
+    pub runs: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub std: f64,
+    pub best: f64,
+    pub mean_time: f64,
+    // Runs that reached the target and their mean time to target
+    pub hits: usize,
+    pub time_to_target: Option<f64>,
+}
+
+impl Statistics {
+    pub fn new(results: &[RunResult]) -> Statistics {
+        let scores: Vec<f64> = results.iter().map(|result| result.score).collect();
+        let times: Vec<f64> = results
+            .iter()
+            .filter_map(|result| result.time_to_target)
+            .collect();
+        let time_to_target = if times.is_empty() {
+            None
+        } else {
+            Some(mean(&times))
+        };
+        Statistics {
+            runs: results.len(),
+            mean: mean(&scores),
+            median: median(&scores),
+            std: std(&scores),
+            best: scores.iter().cloned().fold(f64::INFINITY, f64::min),
+            mean_time: mean(
+                &results
+                    .iter()
+                    .map(|result| result.time)
+                    .collect::<Vec<f64>>(),
+            ),
+            hits: times.len(),
+            time_to_target,
+        }
+    }
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+pub fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    } else {
+        sorted[middle]
+    }
+}
+
+/// Sample standard deviation
+pub fn std(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let sum: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    (sum / (values.len() - 1) as f64).sqrt()
+}
+
+pub struct RankSumTest {
+    pub u: f64,
+    pub z: f64,
+    pub p_value: f64,
+}
+
+/// Two-sided Wilcoxon rank-sum / Mann-Whitney U test. Uses the normal
+/// approximation with tie and continuity correction, so the p-value is
+/// only reliable for around 8 or more runs per sample.
+pub fn rank_sum_test(a: &[f64], b: &[f64]) -> RankSumTest {
+    let n_a = a.len() as f64;
+    let n_b = b.len() as f64;
+    let n = n_a + n_b;
+
+    // Rank the combined samples, ties get the average rank
+    let mut values: Vec<(f64, bool)> = a.iter().map(|value| (*value, true)).collect();
+    values.extend(b.iter().map(|value| (*value, false)));
+    values.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+    let mut rank_sum_a = 0.0;
+    let mut tie_sum = 0.0;
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1].0 == values[i].0 {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        let ties = (j - i + 1) as f64;
+        tie_sum += ties.powi(3) - ties;
+        rank_sum_a += rank * values[i..=j].iter().filter(|value| value.1).count() as f64;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n_a * (n_a + 1.0) / 2.0;
+    let mean = n_a * n_b / 2.0;
+    let variance = n_a * n_b / 12.0 * ((n + 1.0) - tie_sum / (n * (n - 1.0)));
+    if n_a == 0.0 || n_b == 0.0 || variance <= 0.0 {
+        return RankSumTest {
+            u,
+            z: 0.0,
+            p_value: 1.0,
+        };
+    }
+    let difference = ((u - mean).abs() - 0.5).max(0.0);
+    let z = (u - mean).signum() * difference / variance.sqrt();
+    RankSumTest {
+        u,
+        z,
+        p_value: erfc(z.abs() / f64::consts::SQRT_2).min(1.0),
+    }
+}
+
+/// Complementary error function for x >= 0, Abramowitz and Stegun 7.1.26
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    polynomial * (-x * x).exp()
+}
+
+pub struct BenchmarkRow {
+    pub instance: String,
+    pub config: String,
+    pub seeds: (u64, u64), // First and last seed of the runs
+    pub optimum: Option<f64>,
+    pub statistics: Statistics,
+    // Comparison against the base config on the same instance
+    pub test: Option<RankSumTest>,
+}
+
+impl BenchmarkRow {
+    fn gap(&self, score: f64) -> Option<f64> {
+        self.optimum
+            .map(|optimum| (score - optimum) / optimum * 100.0)
+    }
+
+    fn get_columns(&self) -> Vec<String> {
+        let optional = |value: Option<f64>, precision: usize| match value {
+            Some(value) => format!("{:.*}", precision, value),
+            None => String::new(),
+        };
+        let statistics = &self.statistics;
+        vec![
+            self.instance.clone(),
+            self.config.clone(),
+            format!("{}-{}", self.seeds.0, self.seeds.1),
+            statistics.runs.to_string(),
+            optional(self.optimum, 2),
+            format!("{:.2}", statistics.best),
+            format!("{:.2}", statistics.mean),
+            format!("{:.2}", statistics.median),
+            format!("{:.2}", statistics.std),
+            optional(self.gap(statistics.best), 2),
+            optional(self.gap(statistics.mean), 2),
+            format!("{:.3}", statistics.mean_time),
+            statistics.hits.to_string(),
+            optional(statistics.time_to_target, 3),
+            optional(self.test.as_ref().map(|test| test.u), 1),
+            optional(self.test.as_ref().map(|test| test.p_value), 4),
+        ]
+    }
+}
+
+const COLUMNS: [&str; 16] = [
+    "instance",
+    "config",
+    "seeds",
+    "runs",
+    "optimum",
+    "best",
+    "mean",
+    "median",
+    "std",
+    "best_gap_%",
+    "mean_gap_%",
+    "time_s",
+    "hits",
+    "time_to_target_s",
+    "u",
+    "p_value",
+];
+
+pub struct Report {
+    pub rows: Vec<BenchmarkRow>,
+}
+
+impl Report {
+    pub fn to_markdown(&self) -> String {
+        let mut output = String::new();
+        writeln!(&mut output, "| {} |", COLUMNS.join(" | ")).unwrap();
+        let separator: Vec<&str> = COLUMNS.iter().map(|_| "---").collect();
+        writeln!(&mut output, "| {} |", separator.join(" | ")).unwrap();
+        for row in self.rows.iter() {
+            let mut columns = row.get_columns();
+            // Mark significant differences against the base config
+            if let Some(test) = &row.test {
+                if test.p_value < ALPHA {
+                    columns[COLUMNS.len() - 1].push_str(" *");
+                }
+            }
+            writeln!(&mut output, "| {} |", columns.join(" | ")).unwrap();
+        }
+        output
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut output = String::new();
+        writeln!(&mut output, "{}", COLUMNS.join(",")).unwrap();
+        for row in self.rows.iter() {
+            writeln!(&mut output, "{}", row.get_columns().join(",")).unwrap();
+        }
+        output
+    }
+
+    /// Writes CSV if the path ends with .csv, otherwise Markdown
+    pub fn write_to_path(&self, file_path: &str) {
+        let content = if file_path.ends_with(".csv") {
+            self.to_csv()
+        } else {
+            self.to_markdown()
+        };
+        if let Err(err) = fs::write(file_path, content) {
+            panic!("Failed to write to file {}. Error: {}", file_path, err);
+        }
+    }
+}
+
+/// The instances in BENCH_PROBLEMS with optima from BENCH_OPTIMAL_PATH,
+/// or the problem in PROBLEM_PATH if the list is empty
+pub fn get_instances(config: &Config) -> Vec<Instance> {
+    if config.bench_problems.is_empty() {
+        return vec![Instance::new(
+            &config.problem_path,
+            &config.optimal_solution_path,
+        )];
+    }
+    config
+        .bench_problems
+        .split(',')
+        .map(|path| path.trim())
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let mut instance = Instance::new(path, "");
+            if !config.bench_optimal_path.is_empty() {
+                let optimal_path = format!("{}/{}.res", config.bench_optimal_path, instance.name);
+                instance = Instance::new(path, &optimal_path);
+            }
+            instance
+        })
+        .collect()
+}
+
+/// Runs the solver once for the configured number of generations,
+/// with the random numbers drawn from the seed
+pub fn run_once(instance: &Instance, config: &Config, seed: u64) -> RunResult {
+    let mut config = config.clone();
+    config.problem_path = instance.problem_path.clone();
+    // Check the target after every generation
+    config.draw_rate = 1;
+    let target = instance.get_target(&config);
+
+    random::seed(seed);
+    let mut problem = Problem::new(&config);
+    let start_time = Instant::now();
+    problem.generate_population(&config);
+    let mut score = f64::INFINITY;
+    let mut time_to_target = None;
+    for _ in 0..config.generations {
+        let solution = problem.simulate(&config);
+        score = solution.score.unwrap();
+        if time_to_target.is_none() && target.is_some_and(|target| score <= target) {
+            time_to_target = Some(start_time.elapsed().as_secs_f64());
+        }
+    }
+    RunResult {
+        seed,
+        score,
+        time: start_time.elapsed().as_secs_f64(),
+        time_to_target,
+    }
+}
+
+/// Runs every instance bench_runs times with the config, and with the
+/// settings in BENCH_COMPARE_PATH applied on top of it if set. Run r uses
+/// the seed bench_seed + r, so both configs get the same seeds.
+pub fn run_benchmark(config: &Config) -> Report {
+    let mut configs = vec![(String::from("base"), config.clone())];
+    if !config.bench_compare_path.is_empty() {
+        let mut compare = config.clone();
+        let result = compare
+            .apply_file(&config.bench_compare_path)
+            .and_then(|_| compare.validate());
+        if let Err(err) = result {
+            panic!("{}", err);
+        }
+        let name = match Path::new(&config.bench_compare_path).file_stem() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => String::from("compare"),
+        };
+        configs.push((name, compare));
+    }
+
+    let mut rows = Vec::new();
+    for instance in get_instances(config).iter() {
+        let mut base_scores: Option<Vec<f64>> = None;
+        for (name, config) in configs.iter() {
+            let results: Vec<RunResult> = (0..config.bench_runs)
+                .map(|run| {
+                    let seed = config.bench_seed.wrapping_add(run as u64);
+                    let result = run_once(instance, config, seed);
+                    println!(
+                        "{} {} run {} (seed {}): {:.2} in {:.2} s",
+                        instance.name,
+                        name,
+                        run + 1,
+                        result.seed,
+                        result.score,
+                        result.time
+                    );
+                    result
+                })
+                .collect();
+            let scores: Vec<f64> = results.iter().map(|result| result.score).collect();
+            let test = base_scores
+                .as_ref()
+                .map(|base_scores| rank_sum_test(base_scores, &scores));
+            if base_scores.is_none() {
+                base_scores = Some(scores);
+            }
+            let seeds = match (results.first(), results.last()) {
+                (Some(first), Some(last)) => (first.seed, last.seed),
+                _ => (config.bench_seed, config.bench_seed),
+            };
+            rows.push(BenchmarkRow {
+                instance: instance.name.clone(),
+                config: name.clone(),
+                seeds,
+                optimum: instance.optimum,
+                statistics: Statistics::new(&results),
+                test,
+            });
+        }
+    }
+    Report { rows }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::InstanceGenerator;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_seeded_runs() {
+        // Unique per process, so concurrent test runs don't share the file
+        let name = format!("genetic-bench-seed-test-{}", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, InstanceGenerator::new(20, 2, 5).generate()).unwrap();
+        let instance = Instance::new(&path.to_string_lossy(), "");
+        let mut config = Config::default();
+        config.population_size = 20;
+        config.population_gen_step = 10;
+        config.generations = 5;
+
+        let first = run_once(&instance, &config, 42);
+        let again = run_once(&instance, &config, 42);
+        assert_eq!(first.seed, 42);
+        assert_eq!(first.score, again.score);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_statistics() {
+        let results: Vec<RunResult> = [4.0, 1.0, 3.0, 2.0]
+            .iter()
+            .map(|score| RunResult {
+                seed: 0,
+                score: *score,
+                time: 1.0,
+                time_to_target: if *score < 2.5 { Some(0.5) } else { None },
+            })
+            .collect();
+        let statistics = Statistics::new(&results);
+        assert_relative_eq!(statistics.mean, 2.5);
+        assert_relative_eq!(statistics.median, 2.5);
+        assert_relative_eq!(statistics.std, 1.290_994, epsilon = 1e-6);
+        assert_relative_eq!(statistics.best, 1.0);
+        assert_eq!(statistics.hits, 2);
+        assert_eq!(statistics.time_to_target, Some(0.5));
+    }
+
+    #[test]
+    fn test_rank_sum() {
+        // Same as scipy.stats.mannwhitneyu with the asymptotic method
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let b = [6.0, 7.0, 8.0, 9.0, 10.0];
+        let test = rank_sum_test(&a, &b);
+        assert_relative_eq!(test.u, 0.0);
+        assert_relative_eq!(test.p_value, 0.012_185, epsilon = 1e-5);
+
+        let a = [1.0, 2.0, 2.0, 3.0, 5.0, 8.0];
+        let b = [2.0, 3.0, 4.0, 5.0, 5.0, 9.0];
+        let test = rank_sum_test(&a, &b);
+        assert_relative_eq!(test.u, 11.5);
+        assert_relative_eq!(test.p_value, 0.328_939, epsilon = 1e-5);
+
+        let test = rank_sum_test(&[1.0, 1.0], &[1.0, 1.0]);
+        assert_relative_eq!(test.p_value, 1.0);
+    }
+}
@@ -1,75 +1,368 @@
-use envconfig::Envconfig;
-use lazy_static::*;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Envconfig)]
-pub struct Config {
-    #[envconfig(from = "PROBLEM_PATH", default = "")]
-    pub problem_path: String,
+use crate::distance::Rounding;
+use crate::problem::WarmStart;
+use crate::solver::Algorithm;
 
-    #[envconfig(from = "OPTIMAL_SOLUTION_PATH", default = "")]
-    pub optimal_solution_path: String,
-
-    #[envconfig(from = "SOLUTION_PATH", default = "")]
-    pub solution_path: String,
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, String),
+    Format(String, String),
+    UnknownKey(String),
+    Parse(String, String, String),
+    Invalid(Vec<String>),
+}
 
-    #[envconfig(from = "LOAD_SOLUTION", default = "false")]
-    pub load_solution: bool,
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "Error reading {}: {}", path, err),
+            ConfigError::Format(path, err) => write!(f, "Error parsing {}: {}", path, err),
+            ConfigError::UnknownKey(key) => write!(f, "Unknown config key {}", key),
+            ConfigError::Parse(key, value, err) => {
+                write!(f, "Invalid value {:?} for {}: {}", value, key, err)
+            }
+            ConfigError::Invalid(errors) => write!(f, "Invalid config: {}", errors.join(", ")),
+        }
+    }
+}
 
-    #[envconfig(from = "SHOW_SOLUTION", default = "false")]
-    pub show_solution: bool,
+impl Error for ConfigError {}
 
-    #[envconfig(from = "SHOW_OPTIMAL_SOLUTION", default = "false")]
-    pub show_optimal_solution: bool,
+fn parse_value<T>(key: &str, value: &str) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|err| ConfigError::Parse(key.to_string(), value.to_string(), err.to_string()))
+}
 
-    #[envconfig(from = "POPULATION_SIZE", default = "50")]
-    pub population_size: usize,
+// Every setting has a field name used in config files, an environment
+// variable and a default value parsed the same way as other values
+macro_rules! config {
+    ($($field:ident: $type:ty = ($env:expr, $default:expr),)*) => {
+        #[derive(Clone, Debug)]
+        pub struct Config {
+            $(pub $field: $type,)*
+        }
 
-    #[envconfig(from = "POPULATION_GEN_STEP", default = "50")]
-    pub population_gen_step: usize,
+        impl Default for Config {
+            fn default() -> Self {
+                Config {
+                    $($field: parse_value($env, $default).unwrap(),)*
+                }
+            }
+        }
 
-    #[envconfig(from = "GENERATIONS", default = "1000")]
-    pub generations: usize,
+        impl Config {
+            /// Sets a setting from either its field name or environment variable name
+            pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+                match key {
+                    $(stringify!($field) | $env => self.$field = parse_value(key, value)?,)*
+                    _ => return Err(ConfigError::UnknownKey(key.to_string())),
+                }
+                Ok(())
+            }
 
-    #[envconfig(from = "DRAW_RATE", default = "1")]
-    pub draw_rate: i32,
+            /// Overrides settings with the environment variables that are set
+            pub fn apply_env(&mut self) -> Result<(), ConfigError> {
+                $(
+                    if let Ok(value) = env::var($env) {
+                        self.set($env, &value)?;
+                    }
+                )*
+                Ok(())
+            }
+        }
+    };
+}
 
-    #[envconfig(from = "VERBOSE", default = "false")]
-    pub verbose: bool,
+config! {
+    problem_path: String = ("PROBLEM_PATH", ""),
+    optimal_solution_path: String = ("OPTIMAL_SOLUTION_PATH", ""),
+    solution_path: String = ("SOLUTION_PATH", ""),
+    schedule_path: String = ("SCHEDULE_PATH", ""),
+    load_solution: bool = ("LOAD_SOLUTION", "false"),
+    warm_start_path: String = ("WARM_START_PATH", ""),
+    warm_start_mode: WarmStart = ("WARM_START_MODE", "seed"),
+    distance_matrix_path: String = ("DISTANCE_MATRIX_PATH", ""),
+    distance_rounding: Rounding = ("DISTANCE_ROUNDING", "exact"),
+    show_solution: bool = ("SHOW_SOLUTION", "false"),
+    show_optimal_solution: bool = ("SHOW_OPTIMAL_SOLUTION", "false"),
+    depot_vehicle_limits: String = ("DEPOT_VEHICLE_LIMITS", ""),
+    vehicle_fixed_cost: f64 = ("VEHICLE_FIXED_COST", "0.0"),
+    minimize_fleet: bool = ("MINIMIZE_FLEET", "false"),
+    pickup_delivery_path: String = ("PICKUP_DELIVERY_PATH", ""),
+    algorithm: Algorithm = ("ALGORITHM", "genetic"),
+    population_size: usize = ("POPULATION_SIZE", "50"),
+    population_gen_step: usize = ("POPULATION_GEN_STEP", "50"),
+    generations: usize = ("GENERATIONS", "1000"),
+    draw_rate: i32 = ("DRAW_RATE", "1"),
+    verbose: bool = ("VERBOSE", "false"),
+    elite_count: usize = ("ELITE_COUNT", "2"),
+    single_swap_mut_rate: f64 = ("SINGLE_SWAP_MUT_RATE", "0.05"),
+    single_swap_mut_max: usize = ("SINGLE_SWAP_MUT_MAX", "2"),
+    vehicle_remove_mut_rate: f64 = ("VEHICLE_REMOVE_MUT_RATE", "0.05"),
+    vehicle_remove_mut_max: usize = ("VEHICLE_REMOVE_MUT_MAX", "1"),
+    route_reverse_mut_rate: f64 = ("ROUTE_REVERSE_MUT_RATE", "0.0"),
+    route_reverse_mut_max: usize = ("ROUTE_REVERSE_MUT_MAX", "1"),
+    route_split_mut_rate: f64 = ("ROUTE_SPLIT_MUT_RATE", "0.0"),
+    route_split_mut_max: usize = ("ROUTE_SPLIT_MUT_MAX", "1"),
+    route_merge_mut_rate: f64 = ("ROUTE_MERGE_MUT_RATE", "0.0"),
+    route_merge_mut_max: usize = ("ROUTE_MERGE_MUT_MAX", "1"),
+    route_transfer_mut_rate: f64 = ("ROUTE_TRANSFER_MUT_RATE", "0.0"),
+    route_transfer_mut_max: usize = ("ROUTE_TRANSFER_MUT_MAX", "1"),
+    reinsert_mut_rate: f64 = ("REINSERT_MUT_RATE", "0.0"),
+    reinsert_mut_max: usize = ("REINSERT_MUT_MAX", "1"),
+    crossover_rate: f64 = ("CROSSOVER_RATE", "1.0"),
+    parent_selection_k: usize = ("PARENT_SELECTION_K", "5"),
+    cws_bias: usize = ("CWS_BIAS", "10"),
+    solver_iterations: usize = ("SOLVER_ITERATIONS", "1000"),
+    annealing_temperature: f64 = ("ANNEALING_TEMPERATURE", "100.0"),
+    annealing_cooling: f64 = ("ANNEALING_COOLING", "0.9995"),
+    tabu_tenure: usize = ("TABU_TENURE", "20"),
+    tabu_candidates: usize = ("TABU_CANDIDATES", "200"),
+    alns_remove_min: f64 = ("ALNS_REMOVE_MIN", "0.05"),
+    alns_remove_max: f64 = ("ALNS_REMOVE_MAX", "0.3"),
+    alns_reaction: f64 = ("ALNS_REACTION", "0.1"),
+    alns_segment: usize = ("ALNS_SEGMENT", "100"),
+    multi_objective: bool = ("MULTI_OBJECTIVE", "false"),
+    pareto_path: String = ("PARETO_PATH", ""),
+    bench_runs: usize = ("BENCH_N", "10"),
+    bench_seed: u64 = ("BENCH_SEED", "1"),
+    bench_problems: String = ("BENCH_PROBLEMS", ""),
+    bench_optimal_path: String = ("BENCH_OPTIMAL_PATH", ""),
+    bench_compare_path: String = ("BENCH_COMPARE_PATH", ""),
+    bench_target_gap: f64 = ("BENCH_TARGET_GAP", "0.05"),
+    bench_output_path: String = ("BENCH_OUTPUT_PATH", ""),
+}
 
-    #[envconfig(from = "ELITE_COUNT", default = "2")]
-    pub elite_count: usize,
+impl Config {
+    /// Loads the file in CONFIG_PATH if it is set, then applies the
+    /// environment variables and validates the result
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut config = match env::var("CONFIG_PATH") {
+            Ok(path) if !path.is_empty() => Config::from_file(&path)?,
+            _ => Config::default(),
+        };
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
 
-    #[envconfig(from = "SINGLE_SWAP_MUT_RATE", default = "0.05")]
-    pub single_swap_mut_rate: f64,
+    /// Reads a JSON file if the path ends with .json, otherwise a TOML file.
+    /// Settings missing from the file keep their default values.
+    pub fn from_file(path: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.apply_file(path)?;
+        Ok(config)
+    }
 
-    #[envconfig(from = "SINGLE_SWAP_MUT_MAX", default = "2")]
-    pub single_swap_mut_max: usize,
+    /// Applies the settings in a TOML or JSON file on top of this config
+    pub fn apply_file(&mut self, path: &str) -> Result<(), ConfigError> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| ConfigError::Io(path.to_string(), err.to_string()))?;
+        let result = match Path::new(path).extension() {
+            Some(extension) if extension == "json" => self.apply_json(&content),
+            _ => self.apply_toml(&content),
+        };
+        // Add the path to format errors
+        result.map_err(|err| match err {
+            ConfigError::Format(_, message) => ConfigError::Format(path.to_string(), message),
+            err => err,
+        })
+    }
 
-    #[envconfig(from = "VEHICLE_REMOVE_MUT_RATE", default = "0.05")]
-    pub vehicle_remove_mut_rate: f64,
+    /// Parses a flat TOML table of settings
+    pub fn from_toml(content: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.apply_toml(content)?;
+        Ok(config)
+    }
 
-    #[envconfig(from = "VEHICLE_REMOVE_MUT_MAX", default = "1")]
-    pub vehicle_remove_mut_max: usize,
+    fn apply_toml(&mut self, content: &str) -> Result<(), ConfigError> {
+        let format_error = |message: String| ConfigError::Format(String::from("TOML"), message);
+        let table = match toml::from_str(content) {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(format_error(String::from("Expected a table"))),
+            Err(err) => return Err(format_error(err.to_string())),
+        };
+        for (key, value) in table.into_iter() {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                    value.to_string()
+                }
+                _ => {
+                    return Err(format_error(format!(
+                        "{} must be a string, number or bool",
+                        key
+                    )))
+                }
+            };
+            self.set(&key, &value)?;
+        }
+        Ok(())
+    }
 
-    #[envconfig(from = "CROSSOVER_RATE", default = "1.0")]
-    pub crossover_rate: f64,
+    /// Parses a flat JSON object of settings
+    pub fn from_json(content: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        config.apply_json(content)?;
+        Ok(config)
+    }
 
-    #[envconfig(from = "PARENT_SELECTION_K", default = "5")]
-    pub parent_selection_k: usize,
+    fn apply_json(&mut self, content: &str) -> Result<(), ConfigError> {
+        let format_error = |message: String| ConfigError::Format(String::from("JSON"), message);
+        let map = match serde_json::from_str(content) {
+            Ok(serde_json::Value::Object(map)) => map,
+            Ok(_) => return Err(format_error(String::from("Expected an object"))),
+            Err(err) => return Err(format_error(err.to_string())),
+        };
+        for (key, value) in map.into_iter() {
+            let value = match value {
+                serde_json::Value::String(value) => value,
+                serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                _ => {
+                    return Err(format_error(format!(
+                        "{} must be a string, number or bool",
+                        key
+                    )))
+                }
+            };
+            self.set(&key, &value)?;
+        }
+        Ok(())
+    }
 
-    #[envconfig(from = "INFEASIBILITY_PENALTY", default = "1000")]
-    pub infeasibility_penalty: i32,
+    /// Checks that the settings can be used together
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors: Vec<String> = Vec::new();
+        let rates = [
+            ("single_swap_mut_rate", self.single_swap_mut_rate),
+            ("vehicle_remove_mut_rate", self.vehicle_remove_mut_rate),
+            ("route_reverse_mut_rate", self.route_reverse_mut_rate),
+            ("route_split_mut_rate", self.route_split_mut_rate),
+            ("route_merge_mut_rate", self.route_merge_mut_rate),
+            ("route_transfer_mut_rate", self.route_transfer_mut_rate),
+            ("reinsert_mut_rate", self.reinsert_mut_rate),
+            ("crossover_rate", self.crossover_rate),
+            ("alns_remove_min", self.alns_remove_min),
+            ("alns_remove_max", self.alns_remove_max),
+            ("alns_reaction", self.alns_reaction),
+        ];
+        for (name, rate) in rates.iter() {
+            if !(0.0..=1.0).contains(rate) {
+                errors.push(format!("{} must be between 0 and 1, got {}", name, rate));
+            }
+        }
+        if self.population_size == 0 {
+            errors.push(String::from("population_size must be positive"));
+        }
+        if self.elite_count >= self.population_size {
+            errors.push(format!(
+                "elite_count ({}) must be less than population_size ({})",
+                self.elite_count, self.population_size
+            ));
+        } else if (self.population_size - self.elite_count) % 2 == 1 {
+            // Children are bred in pairs
+            errors.push(format!(
+                "population_size - elite_count must be even, got {} - {}",
+                self.population_size, self.elite_count
+            ));
+        }
+        if self.population_gen_step == 0 {
+            errors.push(String::from("population_gen_step must be positive"));
+        }
+        if self.parent_selection_k == 0 {
+            errors.push(String::from("parent_selection_k must be positive"));
+        }
+        if self.cws_bias == 0 {
+            errors.push(String::from("cws_bias must be positive"));
+        }
+        if self.alns_remove_min > self.alns_remove_max {
+            errors.push(String::from(
+                "alns_remove_min must not be greater than alns_remove_max",
+            ));
+        }
+        if self.alns_segment == 0 {
+            errors.push(String::from("alns_segment must be positive"));
+        }
+        if self.annealing_temperature <= 0.0 {
+            errors.push(String::from("annealing_temperature must be positive"));
+        }
+        if self.annealing_cooling <= 0.0 || self.annealing_cooling > 1.0 {
+            errors.push(String::from("annealing_cooling must be in (0, 1]"));
+        }
+        if self.vehicle_fixed_cost < 0.0 {
+            errors.push(String::from("vehicle_fixed_cost must not be negative"));
+        }
+        if self.bench_runs == 0 {
+            errors.push(String::from("bench_runs must be positive"));
+        }
+        if self.bench_target_gap < 0.0 {
+            errors.push(String::from("bench_target_gap must not be negative"));
+        }
 
-    #[envconfig(from = "CWS_BIAS", default = "10")]
-    pub cws_bias: usize,
-}
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
 
-impl Config {
     pub fn update(&mut self, crossover: f64) {
         self.crossover_rate = crossover;
     }
 }
 
-lazy_static! {
-    pub static ref CONFIG: Config = Config::init().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_from_toml_and_json() {
+        let toml = "population_size = 20\nCROSSOVER_RATE = 0.5\nalgorithm = \"alns\"";
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.population_size, 20);
+        assert_eq!(config.crossover_rate, 0.5);
+        assert_eq!(config.algorithm, Algorithm::Alns);
+        assert_eq!(config.elite_count, Config::default().elite_count);
+
+        let json = r#"{"population_size": 30, "multi_objective": true}"#;
+        let config = Config::from_json(json).unwrap();
+        assert_eq!(config.population_size, 30);
+        assert!(config.multi_objective);
+
+        assert!(Config::from_toml("unknown_key = 1").is_err());
+        assert!(Config::from_toml("population_size = \"many\"").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Config::default().validate().is_ok());
+
+        let mut config = Config::default();
+        config.crossover_rate = 1.5;
+        config.elite_count = config.population_size;
+        match config.validate() {
+            Err(ConfigError::Invalid(errors)) => assert_eq!(errors.len(), 2),
+            _ => panic!("Expected the config to be invalid"),
+        }
+
+        let mut config = Config::default();
+        config.elite_count = config.population_size - 3;
+        match config.validate() {
+            Err(ConfigError::Invalid(errors)) => assert_eq!(errors.len(), 1),
+            _ => panic!("Expected the config to be invalid"),
+        }
+    }
 }
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+use crate::config::Config;
+use crate::distance::{DistanceMatrix, Rounding};
+use crate::problem::{Customer, Depot, Model, Problem};
+use crate::solution::Solution;
+use crate::utils::Pos;
+
+/// Reads a CVRPLIB/TSPLIB .vrp file. Several depots can be listed in the
+/// DEPOT_SECTION, in which case every depot gets the same capacity and
+/// number of vehicles.
+///
+/// Customers are numbered 1..=N in the order they appear in the file,
+/// skipping the depots, and the depots are numbered N+1..=N+D. When the
+/// depots are listed first, as in all CVRPLIB instances, the customer
+/// numbers are the same as the ones used in CVRPLIB solution files.
+pub fn load(path: &str, config: &Config) -> Problem {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            panic!("Error opening file {}. Error: {}", path, err);
+        }
+    };
+    parse(&content, config)
+}
+
+pub fn parse(content: &str, config: &Config) -> Problem {
+    let mut header: HashMap<String, String> = HashMap::new();
+    let mut sections: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut section: Option<String> = None;
+
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line == "EOF" {
+            continue;
+        }
+        let first_word = line.split_whitespace().next().unwrap_or("");
+        if first_word.ends_with("_SECTION") {
+            section = Some(first_word.to_string());
+            continue;
+        }
+        match &section {
+            Some(name) => {
+                let values = sections.entry(name.clone()).or_default();
+                for value in line.split_whitespace() {
+                    match value.parse::<f64>() {
+                        Ok(val) => values.push(val),
+                        Err(_) => panic!("Error parsing line {}: {}", line_number + 1, line),
+                    }
+                }
+            }
+            None => {
+                if let Some(index) = line.find(':') {
+                    let key = line[..index].trim().to_uppercase();
+                    let value = line[index + 1..].trim().to_string();
+                    header.insert(key, value);
+                }
+            }
+        }
+    }
+
+    let get_header = |key: &str| -> Option<f64> {
+        header.get(key).map(|value| match value.parse::<f64>() {
+            Ok(val) => val,
+            Err(_) => panic!("Error parsing {}: {}", key, value),
+        })
+    };
+
+    let dimension = match get_header("DIMENSION") {
+        Some(dimension) => dimension as usize,
+        None => panic!("Missing DIMENSION in problem file"),
+    };
+    let capacity = match get_header("CAPACITY") {
+        Some(capacity) => capacity as i32,
+        None => panic!("Missing CAPACITY in problem file"),
+    };
+    let service_time = get_header("SERVICE_TIME").map(|time| time as i32);
+    // Maximum route length
+    let max_duration = get_header("DISTANCE").map(|distance| distance as i32);
+
+    // Nodes are numbered from 1 in the file
+    let mut depot_nodes: Vec<usize> = match sections.get("DEPOT_SECTION") {
+        Some(values) => values
+            .iter()
+            .take_while(|value| **value >= 0.0)
+            .map(|value| *value as usize)
+            .collect(),
+        None => vec![1],
+    };
+    depot_nodes.sort();
+    depot_nodes.dedup();
+
+    let mut demands: Vec<i32> = vec![0; dimension];
+    if let Some(values) = sections.get("DEMAND_SECTION") {
+        for pair in values.chunks(2) {
+            demands[pair[0] as usize - 1] = pair[1] as i32;
+        }
+    }
+
+    let mut coordinates: Vec<(f64, f64)> = vec![(0.0, 0.0); dimension];
+    if let Some(values) = sections.get("NODE_COORD_SECTION") {
+        for node in values.chunks(3) {
+            coordinates[node[0] as usize - 1] = (node[1], node[2]);
+        }
+    }
+
+    // Original node index for every customer followed by every depot
+    let customer_nodes: Vec<usize> = (1..=dimension)
+        .filter(|node| !depot_nodes.contains(node))
+        .collect();
+    let order: Vec<usize> = customer_nodes
+        .iter()
+        .chain(depot_nodes.iter())
+        .map(|node| node - 1)
+        .collect();
+    let num_customers = customer_nodes.len();
+
+    let to_pos = |node: usize| {
+        let (x, y) = coordinates[node];
+        Pos {
+            x: x.round() as i32,
+            y: y.round() as i32,
+        }
+    };
+
+    let customers: Vec<Customer> = customer_nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| {
+            Customer::new(
+                i as i32 + 1,
+                to_pos(node - 1),
+                service_time,
+                demands[node - 1],
+            )
+        })
+        .collect();
+
+    let max_vehicles = match get_header("VEHICLES") {
+        Some(vehicles) => vehicles as i32,
+        // The fleet is shared between the depots
+        None => match vehicles_from_name(header.get("NAME")) {
+            Some(vehicles) => {
+                let depots = depot_nodes.len() as i32;
+                ((vehicles + depots - 1) / depots).max(1)
+            }
+            None => {
+                let total_demand: i32 = customers.iter().map(|c| c.get_demand()).sum();
+                let per_depot = total_demand as f64 / (capacity as f64 * depot_nodes.len() as f64);
+                per_depot.ceil().max(1.0) as i32
+            }
+        },
+    };
+
+    let depots: Vec<Depot> = depot_nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| Depot {
+            capacity,
+            number: (num_customers + i + 1) as i32,
+            pos: to_pos(node - 1),
+            max_vehicles,
+            max_duration,
+        })
+        .collect();
+
+    let weight_type = match header.get("EDGE_WEIGHT_TYPE") {
+        Some(weight_type) => weight_type.to_uppercase(),
+        None => String::from("EUC_2D"),
+    };
+    let matrix = match weight_type.as_str() {
+        "EXPLICIT" => {
+            let format = match header.get("EDGE_WEIGHT_FORMAT") {
+                Some(format) => format.to_uppercase(),
+                None => String::from("FULL_MATRIX"),
+            };
+            let values = match sections.get("EDGE_WEIGHT_SECTION") {
+                Some(values) => values,
+                None => panic!("Missing EDGE_WEIGHT_SECTION in problem file"),
+            };
+            DistanceMatrix::from_explicit(&format, dimension, values)
+        }
+        "EUC_2D" | "CEIL_2D" | "EXACT_2D" => {
+            let mut matrix = DistanceMatrix::new(dimension);
+            for (i, (x1, y1)) in coordinates.iter().enumerate() {
+                for (j, (x2, y2)) in coordinates.iter().enumerate() {
+                    let distance = ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt();
+                    let distance = match weight_type.as_str() {
+                        "EUC_2D" => Rounding::Rounded.apply(distance),
+                        "CEIL_2D" => distance.ceil(),
+                        _ => distance,
+                    };
+                    matrix.set(i, j, distance);
+                }
+            }
+            matrix
+        }
+        _ => panic!("Unsupported EDGE_WEIGHT_TYPE: {}", weight_type),
+    };
+
+    // Reorder the matrix so customers come before depots
+    let mut distance_matrix = DistanceMatrix::new(dimension);
+    for (i, from) in order.iter().enumerate() {
+        for (j, to) in order.iter().enumerate() {
+            distance_matrix.set(i, j, matrix.get(*from, *to));
+        }
+    }
+
+    Problem::from_parts(
+        max_vehicles,
+        depots,
+        customers,
+        Some(distance_matrix),
+        config,
+    )
+}
+
+/// CVRPLIB instance names end with the size of the fleet, e.g. X-n101-k25
+fn vehicles_from_name(name: Option<&String>) -> Option<i32> {
+    let name = name?;
+    let index = name.rfind("-k")?;
+    name[index + 2..].parse::<i32>().ok()
+}
+
+/// Formats the solution in the CVRPLIB .sol format. The cost is the total
+/// route distance, without penalties or vehicle costs, so it can be compared
+/// with the published solutions.
+pub fn format_solution(solution: &Solution, model: &Model) -> String {
+    let mut output = String::new();
+    let routes = solution.routes.iter().filter(|route| route.len() > 2);
+    for (i, route) in routes.enumerate() {
+        let customers: Vec<String> = route[1..(route.len() - 1)]
+            .iter()
+            .map(|customer| customer.to_string())
+            .collect();
+        writeln!(&mut output, "Route #{}: {}", i + 1, customers.join(" ")).unwrap();
+    }
+    let cost: f64 = solution
+        .routes
+        .iter()
+        .filter(|route| route.len() > 2)
+        .map(|route| solution.evaluate_route(route, model).1)
+        .sum();
+    writeln!(&mut output, "Cost {}", cost).unwrap();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSTANCE: &str = "NAME : test-n4-k2\n\
+                            TYPE : CVRP\n\
+                            DIMENSION : 4\n\
+                            EDGE_WEIGHT_TYPE : EUC_2D\n\
+                            CAPACITY : 10\n\
+                            DISTANCE : 50\n\
+                            NODE_COORD_SECTION\n\
+                            1 0 0\n2 3 4\n3 6 8\n4 0 5\n\
+                            DEMAND_SECTION\n\
+                            1 0\n2 4\n3 6\n4 7\n\
+                            DEPOT_SECTION\n\
+                            1\n-1\n\
+                            EOF\n";
+
+    #[test]
+    fn test_parse() {
+        let problem = parse(INSTANCE, &Config::default());
+        assert_eq!(problem.num_customers, 3);
+        assert_eq!(problem.num_depots, 1);
+        // The number of vehicles comes from the name
+        assert_eq!(problem.max_vehicles, 2);
+
+        // The depot is listed first, so the customers keep their order
+        let customers = problem.get_customers();
+        assert_eq!(customers[&1], (3, 4));
+        assert_eq!(customers[&3], (0, 5));
+        let depot = problem.get_depot(4);
+        assert_eq!(depot.pos, Pos { x: 0, y: 0 });
+        assert_eq!(depot.capacity, 10);
+        assert_eq!(depot.max_duration, Some(50));
+
+        let model = problem.get_model();
+        assert_eq!(model.get_demand(1), 4);
+        assert_eq!(model.get_demand(3), 7);
+        assert_eq!(model.get_distance(1, 2), 5.0);
+        // EUC_2D distances are rounded to the nearest integer
+        assert_eq!(model.get_distance(2, 3), 7.0);
+        let vehicle = problem.vehicles[0].number as usize;
+        assert_eq!(model.get_distance(vehicle, 1), 5.0);
+    }
+
+    #[test]
+    fn test_vehicles_per_depot() {
+        // The fleet from the name is split between the depots
+        let instance = INSTANCE
+            .replace("n4-k2", "n4-k3")
+            .replace("1\n-1", "1\n4\n-1");
+        let problem = parse(&instance, &Config::default());
+        assert_eq!(problem.num_depots, 2);
+        assert_eq!(problem.max_vehicles, 2);
+    }
+
+    #[test]
+    fn test_format_solution() {
+        let problem = parse(INSTANCE, &Config::default());
+        let (v1, v2) = (problem.vehicles[0].number, problem.vehicles[1].number);
+        let mut solution = Solution::new(vec![vec![v1, 1, 2, v1], vec![v2, 3, v2]]);
+        // The penalized score is not written
+        solution.score = Some(1027.0);
+        assert_eq!(
+            format_solution(&solution, problem.get_model()),
+            "Route #1: 1 2\nRoute #2: 3\nCost 30\n"
+        );
+    }
+}
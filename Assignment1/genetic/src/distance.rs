@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+
+use crate::utils::Pos;
+
+/// How distances are rounded before they are used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rounding {
+    Exact,
+    // Nearest integer, as in the TSPLIB nint function
+    Rounded,
+    Truncated,
+}
+
+impl Rounding {
+    pub fn apply(&self, value: f64) -> f64 {
+        match self {
+            Rounding::Exact => value,
+            Rounding::Rounded => (value + 0.5).floor(),
+            Rounding::Truncated => value.trunc(),
+        }
+    }
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "exact" => Ok(Rounding::Exact),
+            "rounded" | "round" | "nint" => Ok(Rounding::Rounded),
+            "truncated" | "truncate" | "floor" => Ok(Rounding::Truncated),
+            _ => Err(format!("Unknown rounding: {}", s)),
+        }
+    }
+}
+
+/// Square cost matrix between the nodes of a problem file, customers
+/// followed by depots. The matrix may be asymmetric, so the cost from
+/// a to b is not necessarily the same as from b to a.
+#[derive(Clone)]
+pub struct DistanceMatrix {
+    pub dimension: usize,
+    values: Vec<f64>,
+}
+
+impl DistanceMatrix {
+    pub fn new(dimension: usize) -> DistanceMatrix {
+        DistanceMatrix {
+            dimension,
+            values: vec![0.0; dimension * dimension],
+        }
+    }
+
+    /// Euclidean distances between the positions
+    pub fn from_positions(positions: &[Pos]) -> DistanceMatrix {
+        let mut matrix = DistanceMatrix::new(positions.len());
+        for (i, pos1) in positions.iter().enumerate() {
+            for (j, pos2) in positions.iter().enumerate() {
+                matrix.set(i, j, pos1.distance_to(pos2));
+            }
+        }
+        matrix
+    }
+
+    /// Loads a matrix from either a TSPLIB file with explicit edge weights
+    /// or a CSV file where row i holds the costs from node i
+    pub fn from_file(path: &str) -> DistanceMatrix {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                panic!("Error opening file {}. Error: {}", path, err);
+            }
+        };
+        if content.contains("EDGE_WEIGHT_SECTION") {
+            DistanceMatrix::from_tsplib(&content)
+        } else {
+            DistanceMatrix::from_csv(&content)
+        }
+    }
+
+    pub fn from_csv(content: &str) -> DistanceMatrix {
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values: Vec<_> = line
+                .split(&[',', ';'][..])
+                .map(|value| value.trim().parse::<f64>())
+                .collect();
+            // Skip header rows before the first row of numbers
+            if rows.is_empty() && values.iter().any(|value| value.is_err()) {
+                continue;
+            }
+            let row = values
+                .into_iter()
+                .enumerate()
+                .map(|(column, value)| match value {
+                    Ok(val) => val,
+                    Err(err) => panic!(
+                        "Error parsing row {}, column {} of the distance matrix: {}",
+                        line_number + 1,
+                        column + 1,
+                        err
+                    ),
+                })
+                .collect();
+            rows.push(row);
+        }
+
+        let dimension = rows.len();
+        let mut matrix = DistanceMatrix::new(dimension);
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != dimension {
+                panic!(
+                    "Row {} of the distance matrix has {} values, expected {}",
+                    i + 1,
+                    row.len(),
+                    dimension
+                );
+            }
+            for (j, value) in row.iter().enumerate() {
+                matrix.set(i, j, *value);
+            }
+        }
+        matrix
+    }
+
+    /// Reads a TSPLIB file with EDGE_WEIGHT_TYPE EXPLICIT
+    pub fn from_tsplib(content: &str) -> DistanceMatrix {
+        let mut header: HashMap<String, String> = HashMap::new();
+        let mut values: Vec<f64> = Vec::new();
+        let mut in_section = false;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line == "EOF" {
+                continue;
+            }
+            if line.starts_with("EDGE_WEIGHT_SECTION") {
+                in_section = true;
+                continue;
+            }
+            if in_section {
+                match line
+                    .split_whitespace()
+                    .map(|value| value.parse::<f64>())
+                    .collect::<Result<Vec<f64>, _>>()
+                {
+                    Ok(row) => values.extend(row),
+                    // Start of the next section
+                    Err(_) => in_section = false,
+                }
+            }
+            if !in_section {
+                if let Some(index) = line.find(':') {
+                    let key = line[..index].trim().to_uppercase();
+                    let value = line[index + 1..].trim().to_uppercase();
+                    header.insert(key, value);
+                }
+            }
+        }
+
+        let dimension = match header.get("DIMENSION").map(|d| d.parse::<usize>()) {
+            Some(Ok(dimension)) => dimension,
+            _ => panic!("Missing DIMENSION in distance matrix file"),
+        };
+        let format = match header.get("EDGE_WEIGHT_FORMAT") {
+            Some(format) => format.clone(),
+            None => String::from("FULL_MATRIX"),
+        };
+        DistanceMatrix::from_explicit(&format, dimension, &values)
+    }
+
+    /// Builds the matrix from the values of a TSPLIB EDGE_WEIGHT_SECTION
+    pub fn from_explicit(format: &str, dimension: usize, values: &[f64]) -> DistanceMatrix {
+        let n = dimension;
+        // Pairs (i, j) in the order the values are listed
+        let indices: Vec<(usize, usize)> = match format {
+            "FULL_MATRIX" => (0..n).flat_map(|i| (0..n).map(move |j| (i, j))).collect(),
+            "UPPER_ROW" | "LOWER_COL" => (0..n)
+                .flat_map(|i| ((i + 1)..n).map(move |j| (i, j)))
+                .collect(),
+            "LOWER_ROW" | "UPPER_COL" => (0..n).flat_map(|i| (0..i).map(move |j| (i, j))).collect(),
+            "UPPER_DIAG_ROW" | "LOWER_DIAG_COL" => {
+                (0..n).flat_map(|i| (i..n).map(move |j| (i, j))).collect()
+            }
+            "LOWER_DIAG_ROW" | "UPPER_DIAG_COL" => {
+                (0..n).flat_map(|i| (0..=i).map(move |j| (i, j))).collect()
+            }
+            _ => panic!("Unsupported EDGE_WEIGHT_FORMAT: {}", format),
+        };
+        if values.len() != indices.len() {
+            panic!(
+                "Expected {} edge weights for {} with dimension {}, found {}",
+                indices.len(),
+                format,
+                dimension,
+                values.len()
+            );
+        }
+
+        let symmetric = format != "FULL_MATRIX";
+        let mut matrix = DistanceMatrix::new(dimension);
+        for ((i, j), value) in indices.into_iter().zip(values.iter()) {
+            matrix.set(i, j, *value);
+            if symmetric {
+                matrix.set(j, i, *value);
+            }
+        }
+        matrix
+    }
+
+    pub fn get(&self, from: usize, to: usize) -> f64 {
+        self.values[from * self.dimension + to]
+    }
+
+    pub fn set(&mut self, from: usize, to: usize, value: f64) {
+        self.values[from * self.dimension + to] = value;
+    }
+
+    pub fn round(&mut self, rounding: Rounding) {
+        for value in self.values.iter_mut() {
+            *value = rounding.apply(*value);
+        }
+    }
+
+    /// Inserts a node at the index. The function returns the costs from and
+    /// to the new node for every existing node.
+    pub fn insert_node<F>(&mut self, index: usize, costs: F)
+    where
+        F: Fn(usize) -> (f64, f64),
+    {
+        // Index of every node in the old matrix
+        let old_index = |i: usize| if i < index { i } else { i - 1 };
+        let mut matrix = DistanceMatrix::new(self.dimension + 1);
+        for i in 0..matrix.dimension {
+            for j in 0..matrix.dimension {
+                let value = if i == index && j == index {
+                    0.0
+                } else if i == index {
+                    costs(old_index(j)).0
+                } else if j == index {
+                    costs(old_index(i)).1
+                } else {
+                    self.get(old_index(i), old_index(j))
+                };
+                matrix.set(i, j, value);
+            }
+        }
+        *self = matrix;
+    }
+
+    pub fn remove_node(&mut self, index: usize) {
+        let new_index = |i: usize| if i < index { i } else { i + 1 };
+        let mut matrix = DistanceMatrix::new(self.dimension - 1);
+        for i in 0..matrix.dimension {
+            for j in 0..matrix.dimension {
+                matrix.set(i, j, self.get(new_index(i), new_index(j)));
+            }
+        }
+        *self = matrix;
+    }
+
+    pub fn is_symmetric(&self) -> bool {
+        (0..self.dimension).all(|i| (0..i).all(|j| self.get(i, j) == self.get(j, i)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matrix_eq(matrix: &DistanceMatrix, expected: &[[f64; 3]; 3]) {
+        assert_eq!(matrix.dimension, 3);
+        for (i, row) in expected.iter().enumerate() {
+            for (j, value) in row.iter().enumerate() {
+                assert_eq!(matrix.get(i, j), *value, "cost from {} to {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_explicit_formats() {
+        let symmetric = [[0.0, 1.0, 2.0], [1.0, 0.0, 3.0], [2.0, 3.0, 0.0]];
+        let formats: [(&str, &[f64]); 8] = [
+            ("UPPER_ROW", &[1.0, 2.0, 3.0]),
+            ("LOWER_COL", &[1.0, 2.0, 3.0]),
+            ("LOWER_ROW", &[1.0, 2.0, 3.0]),
+            ("UPPER_COL", &[1.0, 2.0, 3.0]),
+            ("UPPER_DIAG_ROW", &[0.0, 1.0, 2.0, 0.0, 3.0, 0.0]),
+            ("LOWER_DIAG_COL", &[0.0, 1.0, 2.0, 0.0, 3.0, 0.0]),
+            ("LOWER_DIAG_ROW", &[0.0, 1.0, 0.0, 2.0, 3.0, 0.0]),
+            ("UPPER_DIAG_COL", &[0.0, 1.0, 0.0, 2.0, 3.0, 0.0]),
+        ];
+        for (format, values) in formats.iter() {
+            let matrix = DistanceMatrix::from_explicit(format, 3, values);
+            assert_matrix_eq(&matrix, &symmetric);
+            assert!(matrix.is_symmetric());
+        }
+
+        let values = [0.0, 1.0, 2.0, 4.0, 0.0, 3.0, 5.0, 6.0, 0.0];
+        let matrix = DistanceMatrix::from_explicit("FULL_MATRIX", 3, &values);
+        assert_matrix_eq(
+            &matrix,
+            &[[0.0, 1.0, 2.0], [4.0, 0.0, 3.0], [5.0, 6.0, 0.0]],
+        );
+        assert!(!matrix.is_symmetric());
+
+        let tsplib = "NAME : test\nTYPE : ATSP\nDIMENSION : 3\nEDGE_WEIGHT_TYPE : EXPLICIT\n\
+                      EDGE_WEIGHT_FORMAT : FULL_MATRIX\nEDGE_WEIGHT_SECTION\n\
+                      0 1 2\n4 0 3\n5 6 0\nEOF\n";
+        assert!(!DistanceMatrix::from_tsplib(tsplib).is_symmetric());
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = "a,b,c\n0,1,2\n4;0;3\n5, 6, 0\n";
+        let matrix = DistanceMatrix::from_csv(csv);
+        assert_matrix_eq(
+            &matrix,
+            &[[0.0, 1.0, 2.0], [4.0, 0.0, 3.0], [5.0, 6.0, 0.0]],
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "row 3, column 2")]
+    fn test_csv_bad_value() {
+        DistanceMatrix::from_csv("a,b,c\n0,1,2\n4,x,3\n5,6,0\n");
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(Rounding::Exact.apply(2.5), 2.5);
+        assert_eq!(Rounding::Rounded.apply(2.5), 3.0);
+        assert_eq!(Rounding::Truncated.apply(2.5), 2.0);
+        assert_eq!(Rounding::Rounded.apply(2.4), 2.0);
+        assert_eq!("nint".parse::<Rounding>(), Ok(Rounding::Rounded));
+        assert!("up".parse::<Rounding>().is_err());
+    }
+
+    #[test]
+    fn test_insert_and_remove_node() {
+        let values = [0.0, 1.0, 2.0, 4.0, 0.0, 3.0, 5.0, 6.0, 0.0];
+        let original = DistanceMatrix::from_explicit("FULL_MATRIX", 3, &values);
+        let mut matrix = original.clone();
+
+        // From the new node to node i costs 10 + i, back costs 20 + i
+        matrix.insert_node(1, |node| (10.0 + node as f64, 20.0 + node as f64));
+        assert_eq!(matrix.dimension, 4);
+        assert_eq!(matrix.get(1, 1), 0.0);
+        assert_eq!(matrix.get(1, 0), 10.0);
+        assert_eq!(matrix.get(1, 3), 12.0);
+        assert_eq!(matrix.get(3, 1), 22.0);
+        assert_eq!(matrix.get(0, 2), 1.0);
+        assert_eq!(matrix.get(3, 2), 6.0);
+
+        matrix.remove_node(1);
+        assert_matrix_eq(
+            &matrix,
+            &[[0.0, 1.0, 2.0], [4.0, 0.0, 3.0], [5.0, 6.0, 0.0]],
+        );
+    }
+}
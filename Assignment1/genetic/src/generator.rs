@@ -0,0 +1,209 @@
+use std::f64::consts::PI;
+use std::fmt::Write;
+use std::fs;
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::utils::Pos;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Distribution {
+    Uniform,
+    Clustered,
+    Mixed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DemandDistribution {
+    Uniform,
+    // Mostly small demands with a few large ones
+    Bimodal,
+}
+
+impl FromStr for Distribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uniform" => Ok(Distribution::Uniform),
+            "clustered" => Ok(Distribution::Clustered),
+            "mixed" => Ok(Distribution::Mixed),
+            _ => Err(format!("Unknown spatial distribution: {}", s)),
+        }
+    }
+}
+
+impl FromStr for DemandDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "uniform" => Ok(DemandDistribution::Uniform),
+            "bimodal" => Ok(DemandDistribution::Bimodal),
+            _ => Err(format!("Unknown demand distribution: {}", s)),
+        }
+    }
+}
+
+/// Generates random MDVRP instances in the same format as the problem files
+pub struct InstanceGenerator {
+    pub num_customers: i32,
+    pub num_depots: i32,
+    pub max_vehicles: i32,
+    pub distribution: Distribution,
+    pub num_clusters: usize,
+    // Coordinates are drawn from [-grid_size, grid_size]
+    pub grid_size: i32,
+    pub demand_distribution: DemandDistribution,
+    pub min_demand: i32,
+    pub max_demand: i32,
+    // Total demand divided by total fleet capacity
+    pub capacity_tightness: f64,
+    // Maximum route duration, 0 means no limit
+    pub max_duration: i32,
+    pub service_time: i32,
+    pub seed: u64,
+}
+
+impl InstanceGenerator {
+    pub fn new(num_customers: i32, num_depots: i32, seed: u64) -> InstanceGenerator {
+        InstanceGenerator {
+            num_customers,
+            num_depots,
+            max_vehicles: 4,
+            distribution: Distribution::Uniform,
+            num_clusters: 5,
+            grid_size: 100,
+            demand_distribution: DemandDistribution::Uniform,
+            min_demand: 1,
+            max_demand: 25,
+            capacity_tightness: 0.8,
+            max_duration: 0,
+            service_time: 0,
+            seed,
+        }
+    }
+
+    /// Generates the content of a problem file
+    pub fn generate(&self) -> String {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let depots: Vec<Pos> = (0..self.num_depots)
+            .map(|_| self.uniform_pos(&mut rng))
+            .collect();
+        let customers = self.customer_positions(&mut rng);
+        let demands: Vec<i32> = (0..self.num_customers)
+            .map(|_| self.demand(&mut rng))
+            .collect();
+
+        let total_demand: i32 = demands.iter().sum();
+        let fleet_size = (self.num_depots * self.max_vehicles) as f64;
+        let capacity = (total_demand as f64 / (self.capacity_tightness * fleet_size)).ceil() as i32;
+        let capacity = capacity.max(self.max_demand);
+
+        let mut output = String::new();
+        writeln!(
+            &mut output,
+            "{} {} {}",
+            self.max_vehicles, self.num_customers, self.num_depots
+        )
+        .unwrap();
+
+        for _ in 0..self.num_depots {
+            writeln!(&mut output, "{} {}", self.max_duration, capacity).unwrap();
+        }
+
+        // Every customer can be served from any depot
+        let depot_combinations: Vec<String> =
+            (0..self.num_depots).map(|i| (1 << i).to_string()).collect();
+        for (i, (pos, demand)) in customers.iter().zip(demands.iter()).enumerate() {
+            writeln!(
+                &mut output,
+                "{} {} {} {} {} 1 {} {}",
+                i + 1,
+                pos.x,
+                pos.y,
+                self.service_time,
+                demand,
+                self.num_depots,
+                depot_combinations.join(" ")
+            )
+            .unwrap();
+        }
+
+        for (i, pos) in depots.iter().enumerate() {
+            writeln!(
+                &mut output,
+                "{} {} {} 0 0 0 0",
+                self.num_customers + i as i32 + 1,
+                pos.x,
+                pos.y
+            )
+            .unwrap();
+        }
+
+        output
+    }
+
+    pub fn write_to_file(&self, path: &str) {
+        if let Err(err) = fs::write(path, self.generate()) {
+            panic!("Failed to write problem file {}. Error: {}", path, err);
+        }
+    }
+
+    fn uniform_pos(&self, rng: &mut StdRng) -> Pos {
+        Pos {
+            x: rng.gen_range(-self.grid_size, self.grid_size + 1),
+            y: rng.gen_range(-self.grid_size, self.grid_size + 1),
+        }
+    }
+
+    fn customer_positions(&self, rng: &mut StdRng) -> Vec<Pos> {
+        let centers: Vec<Pos> = (0..self.num_clusters.max(1))
+            .map(|_| self.uniform_pos(rng))
+            .collect();
+        // Standard deviation of the distance from a cluster center
+        let spread = self.grid_size as f64 / 10.0;
+
+        (0..self.num_customers)
+            .map(|i| {
+                let clustered = match self.distribution {
+                    Distribution::Uniform => false,
+                    Distribution::Clustered => true,
+                    Distribution::Mixed => i % 2 == 0,
+                };
+                if !clustered {
+                    return self.uniform_pos(rng);
+                }
+                let center = &centers[rng.gen_range(0, centers.len())];
+                // Box-Muller transform for normally distributed offsets
+                let u1: f64 = 1.0 - rng.gen::<f64>();
+                let u2: f64 = rng.gen();
+                let radius = spread * (-2.0 * u1.ln()).sqrt();
+                let angle = 2.0 * PI * u2;
+                let x = center.x + (radius * angle.cos()).round() as i32;
+                let y = center.y + (radius * angle.sin()).round() as i32;
+                Pos {
+                    x: x.max(-self.grid_size).min(self.grid_size),
+                    y: y.max(-self.grid_size).min(self.grid_size),
+                }
+            })
+            .collect()
+    }
+
+    fn demand(&self, rng: &mut StdRng) -> i32 {
+        match self.demand_distribution {
+            DemandDistribution::Uniform => rng.gen_range(self.min_demand, self.max_demand + 1),
+            DemandDistribution::Bimodal => {
+                let split = self.min_demand + (self.max_demand - self.min_demand) / 4;
+                if rng.gen::<f64>() < 0.8 {
+                    rng.gen_range(self.min_demand, split + 1)
+                } else {
+                    rng.gen_range(split, self.max_demand + 1)
+                }
+            }
+        }
+    }
+}
@@ -4,15 +4,17 @@ use std::fmt;
 
 use lazysort::SortedBy;
 use rand::seq::SliceRandom;
-use rand::{self, Rng};
+use rand::Rng;
 
 use crate::config::Config;
 use crate::problem::{Model, Problem, Vehicle};
+use crate::random;
 use crate::simulation::{Chromosome, Gene};
 
 struct Savings {
     pub dim: usize,
     pub vec: Vec<f64>,
+    pub feasible: Vec<bool>, // The merged route does not exceed the capacity
 }
 
 impl Savings {
@@ -20,11 +22,13 @@ impl Savings {
         Savings {
             dim: dim,
             vec: vec![-100_000.0; dim * dim],
+            feasible: vec![false; dim * dim],
         }
     }
 
-    pub fn change(&mut self, i: usize, j: usize, new_value: f64) {
+    pub fn change(&mut self, i: usize, j: usize, new_value: f64, feasible: bool) {
         self.vec[i * self.dim + j] = new_value;
+        self.feasible[i * self.dim + j] = feasible;
     }
 
     pub fn get_indices(&self, number: usize) -> (usize, usize) {
@@ -38,6 +42,7 @@ impl Savings {
 struct Route {
     pub cost: Option<f64>,
     pub customers: Vec<i32>,
+    pub feasible: bool,
 }
 
 impl fmt::Display for Route {
@@ -55,7 +60,7 @@ impl fmt::Display for Route {
 }
 
 impl Route {
-    pub fn evaluate(&mut self, config: &Config, model: &Model, vehicle: &Vehicle) {
+    pub fn evaluate(&mut self, model: &Model, vehicle: &Vehicle) {
         // Cost already calculated
         if let Some(_) = self.cost {
             return;
@@ -67,14 +72,24 @@ impl Route {
         for customer_number in self.customers.iter() {
             score += model.get_distance(current_node, *customer_number as usize);
             capacity_left -= model.get_demand(*customer_number as usize);
-            if capacity_left < 0 {
-                score += config.infeasibility_penalty as f64;
+            if capacity_left < 0 && !model.has_pickups {
+                score += model.capacity_penalty;
             }
 
             current_node = *customer_number as usize;
         }
         // Add distane back to depot
         score += model.get_distance(current_node, start_node);
+        if !self.customers.is_empty() {
+            score += model.vehicle_cost;
+        }
+        self.feasible = capacity_left >= 0;
+        if model.has_pickups {
+            // The load goes up and down, so count load and precedence violations
+            let violations = model.load_violations(start_node, &self.customers);
+            score += violations as f64 * model.capacity_penalty;
+            self.feasible = violations == 0;
+        }
         self.cost = Some(score);
     }
 
@@ -98,23 +113,19 @@ fn single_customers_routes(customers: Vec<i32>) -> Vec<Route> {
             Route {
                 cost: None,
                 customers: customers,
+                feasible: true,
             }
         })
         .collect()
 }
 
-fn evaluate_routes(config: &Config, routes: &mut Vec<Route>, model: &Model, vehicle: &Vehicle) {
+fn evaluate_routes(routes: &mut Vec<Route>, model: &Model, vehicle: &Vehicle) {
     for route in routes.iter_mut() {
-        route.evaluate(config, model, vehicle);
+        route.evaluate(model, vehicle);
     }
 }
 
-fn calculate_savings(
-    config: &Config,
-    routes: &Vec<Route>,
-    model: &Model,
-    vehicle: &Vehicle,
-) -> Savings {
+fn calculate_savings(routes: &Vec<Route>, model: &Model, vehicle: &Vehicle) -> Savings {
     let num_routes = routes.len();
     let mut savings = Savings::new(num_routes);
 
@@ -124,9 +135,9 @@ fn calculate_savings(
                 continue;
             }
             let mut merged_route = merge_routes(r1, r2);
-            merged_route.evaluate(config, model, vehicle);
+            merged_route.evaluate(model, vehicle);
             let saving = r1.get_cost() + r2.get_cost() - merged_route.get_cost();
-            savings.change(i1, i2, saving);
+            savings.change(i1, i2, saving, merged_route.feasible);
         }
     }
 
@@ -144,6 +155,7 @@ fn merge_routes(r1: &Route, r2: &Route) -> Route {
     Route {
         cost: None,
         customers: merged,
+        feasible: true,
     }
 }
 
@@ -160,7 +172,7 @@ fn remove_routes(routes: &mut Vec<Route>, index_one: usize, index_two: usize) {
     routes.remove(min_index);
 }
 
-fn sort_savings(config: &Config, savings: &Savings) -> Vec<(usize, f64)> {
+fn sort_savings(config: &Config, savings: &Savings, feasible_only: bool) -> Vec<(usize, f64)> {
     let sorted_savings: Vec<(usize, f64)> = savings
         .vec
         .iter()
@@ -168,7 +180,7 @@ fn sort_savings(config: &Config, savings: &Savings) -> Vec<(usize, f64)> {
         .enumerate()
         .filter(|(i, _)| {
             let (x, y) = savings.get_indices(*i);
-            x != y
+            x != y && (savings.feasible[*i] || !feasible_only)
         })
         .sorted_by(|a, b| b.1.partial_cmp(&a.1).unwrap())
         .take(config.cws_bias)
@@ -180,7 +192,7 @@ fn sort_savings(config: &Config, savings: &Savings) -> Vec<(usize, f64)> {
 fn select_routes_to_merge(config: &Config, sorted_savings: &Vec<(usize, f64)>) -> usize {
     let cws_bias = cmp::min(config.cws_bias, sorted_savings.len());
 
-    let mut rng = rand::thread_rng();
+    let mut rng = random::rng();
     let index = rng.gen_range(0, cws_bias);
     if index >= sorted_savings.len() {
         panic!("This is not allowed!");
@@ -205,28 +217,37 @@ fn positive_saving_left(sorted_savings: &Vec<(usize, f64)>) -> bool {
 
 pub fn savings_init(config: &Config, model: &Model, problem: &Problem) -> Vec<Vec<i32>> {
     let mut initial_solution = Vec::new();
-    let depot_map = problem.map_customers_to_depot();
+    // In depot order, so runs with the same seed give the same routes
+    let mut depot_map: Vec<_> = problem.map_customers_to_depot().into_iter().collect();
+    depot_map.sort_by_key(|(depot, _)| depot.number);
     for (depot, customers) in depot_map.iter() {
         let customers = customers.iter().map(|c| c.number).collect();
         let mut routes = single_customers_routes(customers);
         let vehicle = problem.get_vehicle_for_depot(depot);
-        evaluate_routes(config, &mut routes, &model, &vehicle);
+        evaluate_routes(&mut routes, &model, &vehicle);
         loop {
-            let savings_matrix = calculate_savings(config, &routes, model, vehicle);
-            let sorted_savings = sort_savings(config, &savings_matrix);
-
-            // Continue merging until we have enough vehicles and there is no saving
-            let enough_vehicles = routes.len() <= problem.max_vehicles as usize;
-            if !positive_saving_left(&sorted_savings) && enough_vehicles {
+            let savings_matrix = calculate_savings(&routes, model, vehicle);
+            let feasible_savings = sort_savings(config, &savings_matrix, true);
+
+            // Continue merging until we have enough vehicles and there is no saving.
+            // Merges that overload a vehicle are only used to get enough vehicles.
+            let enough_vehicles = routes.len() <= depot.max_vehicles as usize;
+            let sorted_savings = if positive_saving_left(&feasible_savings)
+                || (!enough_vehicles && !feasible_savings.is_empty())
+            {
+                feasible_savings
+            } else if !enough_vehicles {
+                sort_savings(config, &savings_matrix, false)
+            } else {
                 break;
-            }
+            };
 
             let i = select_routes_to_merge(config, &sorted_savings);
             let (i, j) = savings_matrix.get_indices(i);
             let route_one = &routes[i];
             let route_two = &routes[j];
             let mut new_route = merge_routes(route_one, route_two);
-            new_route.evaluate(config, model, vehicle);
+            new_route.evaluate(model, vehicle);
             remove_routes(&mut routes, i, j);
             routes.push(new_route);
         }
@@ -256,14 +277,53 @@ pub fn random_init(model: &Model, problem: &Problem) -> Chromosome {
     let mut genes: Vec<Gene> = Vec::new();
 
     for i in 1..=problem.num_customers {
-        genes.push(Gene::Customer(i));
+        genes.push(Gene::customer(i));
     }
     for i in 1..=problem.vehicles.len() {
-        genes.push(Gene::Depot(i as i32 + problem.num_customers));
+        genes.push(Gene::depot(i as i32 + problem.num_customers));
     }
 
-    let mut rng = rand::thread_rng();
+    let mut rng = random::rng();
     genes.shuffle(&mut rng);
 
     Chromosome { genes, score: None }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::InstanceGenerator;
+    use crate::parser;
+
+    #[test]
+    fn test_savings_init() {
+        let mut generator = InstanceGenerator::new(30, 2, 3);
+        // Plenty of spare capacity, so feasible merges always exist
+        generator.capacity_tightness = 0.4;
+        let lines = parser::load_str(&generator.generate());
+        let config = Config::default();
+        let problem = Problem::from_lines(&lines, &config);
+        let model = problem.get_model();
+
+        for seed in 0..5 {
+            random::seed(seed);
+            let routes = savings_init(&config, model, &problem);
+            // One route for every vehicle, starting and ending at the vehicle
+            assert_eq!(routes.len(), problem.vehicles.len());
+            let mut customers: Vec<i32> = Vec::new();
+            for (route, vehicle) in routes.iter().zip(problem.vehicles.iter()) {
+                assert_eq!(route.first(), Some(&vehicle.number));
+                assert_eq!(route.last(), Some(&vehicle.number));
+                let route = &route[1..(route.len() - 1)];
+                let load: i32 = route.iter().map(|c| model.get_demand(*c as usize)).sum();
+                assert!(load <= vehicle.capacity, "Route {:?} is overloaded", route);
+                customers.extend(route);
+            }
+
+            // Every customer is visited exactly once
+            customers.sort();
+            let expected: Vec<i32> = (1..=problem.num_customers).collect();
+            assert_eq!(customers, expected);
+        }
+    }
+}
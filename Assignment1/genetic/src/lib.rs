@@ -1,10 +1,19 @@
-#[macro_use]
-extern crate envconfig_derive;
-
+pub mod alns;
+pub mod annealing;
+pub mod benchmark;
 pub mod config;
+pub mod cvrplib;
+pub mod distance;
+pub mod generator;
 pub mod heuristic;
+pub mod pareto;
 pub mod parser;
+pub mod plan;
 pub mod problem;
+pub mod random;
+pub mod schedule;
 pub mod simulation;
 pub mod solution;
+pub mod solver;
+pub mod tabu;
 pub mod utils;
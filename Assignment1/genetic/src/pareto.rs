@@ -0,0 +1,341 @@
+use std::cmp::Ordering;
+use std::f64;
+
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::config::Config;
+use crate::problem::{CustomerChange, Model};
+use crate::random;
+use crate::simulation::{self, Chromosome, Decode};
+use crate::solution::Solution;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Objectives {
+    pub distance: f64,
+    pub vehicles: f64,
+    pub longest_route: f64,
+}
+
+impl Objectives {
+    pub fn new(distance: f64, vehicles: f64, longest_route: f64) -> Objectives {
+        Objectives {
+            distance,
+            vehicles,
+            longest_route,
+        }
+    }
+
+    pub fn get_values(&self) -> [f64; 3] {
+        [self.distance, self.vehicles, self.longest_route]
+    }
+
+    /// All objectives are minimized. Self dominates other if it is no worse
+    /// in every objective and strictly better in at least one.
+    pub fn dominates(&self, other: &Objectives) -> bool {
+        let v1 = self.get_values();
+        let v2 = other.get_values();
+        let mut better = false;
+        for (a, b) in v1.iter().zip(v2.iter()) {
+            if a > b {
+                return false;
+            }
+            if a < b {
+                better = true;
+            }
+        }
+        better
+    }
+}
+
+/// Multi-objective simulation using NSGA-II
+pub struct ParetoSimulation {
+    pub chromosomes: Vec<Chromosome>,
+    pub objectives: Vec<Objectives>,
+    pub fronts: Vec<Vec<usize>>,
+    ranks: Vec<usize>,
+    crowding: Vec<f64>,
+    pub generation: i32,
+}
+
+impl ParetoSimulation {
+    pub fn new(chromosomes: Vec<Chromosome>, model: &Model) -> ParetoSimulation {
+        let mut simulation = ParetoSimulation {
+            chromosomes,
+            objectives: Vec::new(),
+            fronts: Vec::new(),
+            ranks: Vec::new(),
+            crowding: Vec::new(),
+            generation: 1,
+        };
+        simulation.evaluate(model);
+        simulation.sort();
+        simulation
+    }
+
+    pub fn size(&self) -> usize {
+        self.chromosomes.len()
+    }
+
+    pub fn evaluate(&mut self, model: &Model) {
+        self.objectives = self
+            .chromosomes
+            .par_iter()
+            .map(|chromosome| chromosome.evaluate_objectives(model))
+            .collect();
+    }
+
+    /// Updates every chromosome after a customer is added or removed
+    pub fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        self.chromosomes
+            .par_iter_mut()
+            .for_each(|chromosome| *chromosome = chromosome.update_customers(change, model));
+        self.evaluate(model);
+        self.sort();
+    }
+
+    /// Sorts the population into non-dominated fronts and calculates
+    /// the crowding distance of every chromosome
+    fn sort(&mut self) {
+        self.fronts = non_dominated_sort(&self.objectives);
+        self.ranks = vec![0; self.objectives.len()];
+        self.crowding = vec![0.0; self.objectives.len()];
+        for (rank, front) in self.fronts.iter().enumerate() {
+            for &index in front.iter() {
+                self.ranks[index] = rank;
+            }
+            for (index, distance) in crowding_distance(front, &self.objectives) {
+                self.crowding[index] = distance;
+            }
+        }
+    }
+
+    /// Returns true if chromosome a is better than b using the crowded comparison
+    fn crowded_less(&self, a: usize, b: usize) -> bool {
+        self.ranks[a] < self.ranks[b]
+            || (self.ranks[a] == self.ranks[b] && self.crowding[a] > self.crowding[b])
+    }
+
+    fn parent_selection(&self, config: &Config) -> &Chromosome {
+        // Selects the best parent out of K random selected parents
+        let mut rng = random::rng();
+        let mut best = rng.gen_range(0, self.size());
+        for _ in 1..config.parent_selection_k {
+            let index = rng.gen_range(0, self.size());
+            if self.crowded_less(index, best) {
+                best = index;
+            }
+        }
+        &self.chromosomes[best]
+    }
+
+    pub fn run(&mut self, model: &Model, config: &Config) {
+        let population_size = self.size();
+        let iterations = population_size / 2 + population_size % 2;
+
+        let mut offspring: Vec<Chromosome> = random::task_seeds(iterations)
+            .into_par_iter()
+            .flat_map(|seed| {
+                random::with_seed(seed, || {
+                    let parent_one = self.parent_selection(config);
+                    let parent_two = self.parent_selection(config);
+                    simulation::breed(parent_one, parent_two, model, config)
+                })
+            })
+            .collect();
+        offspring.truncate(population_size);
+
+        let mut offspring_objectives: Vec<Objectives> = offspring
+            .par_iter()
+            .map(|chromosome| chromosome.evaluate_objectives(model))
+            .collect();
+
+        // Combine parents and offspring and select the next population
+        let mut chromosomes = std::mem::take(&mut self.chromosomes);
+        let mut objectives = std::mem::take(&mut self.objectives);
+        chromosomes.append(&mut offspring);
+        objectives.append(&mut offspring_objectives);
+
+        let mut selected: Vec<usize> = Vec::with_capacity(population_size);
+        for front in non_dominated_sort(&objectives).iter() {
+            if selected.len() + front.len() <= population_size {
+                selected.extend(front.iter());
+            } else {
+                let mut crowding = crowding_distance(front, &objectives);
+                crowding.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+                let missing = population_size - selected.len();
+                selected.extend(crowding.iter().take(missing).map(|(index, _)| *index));
+            }
+            if selected.len() == population_size {
+                break;
+            }
+        }
+
+        let mut keep = vec![false; chromosomes.len()];
+        for index in selected {
+            keep[index] = true;
+        }
+        for (index, (chromosome, objective)) in chromosomes.into_iter().zip(objectives).enumerate()
+        {
+            if keep[index] {
+                self.chromosomes.push(chromosome);
+                self.objectives.push(objective);
+            }
+        }
+        self.sort();
+
+        self.generation += 1;
+    }
+
+    /// Returns one solution for each distinct objective vector in the
+    /// first front, ordered by total distance
+    pub fn get_pareto_set(&self) -> Vec<Solution> {
+        let mut front: Vec<usize> = match self.fronts.first() {
+            Some(front) => front.clone(),
+            None => Vec::new(),
+        };
+        front.sort_by(|a, b| {
+            let (a, b) = (&self.objectives[*a], &self.objectives[*b]);
+            a.get_values()
+                .partial_cmp(&b.get_values())
+                .unwrap_or(Ordering::Equal)
+        });
+        front.dedup_by(|a, b| self.objectives[*a] == self.objectives[*b]);
+
+        front
+            .into_iter()
+            .map(|index| {
+                let mut solution = self.chromosomes[index].decode();
+                solution.score = Some(self.objectives[index].distance);
+                solution
+            })
+            .collect()
+    }
+}
+
+/// Deb's fast non-dominated sort. Returns the indices of each front,
+/// starting with the non-dominated front.
+pub fn non_dominated_sort(objectives: &[Objectives]) -> Vec<Vec<usize>> {
+    let size = objectives.len();
+    let mut dominated_by: Vec<Vec<usize>> = vec![Vec::new(); size];
+    let mut domination_count: Vec<usize> = vec![0; size];
+    let mut fronts: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+
+    for p in 0..size {
+        for q in 0..size {
+            if objectives[p].dominates(&objectives[q]) {
+                dominated_by[p].push(q);
+            } else if objectives[q].dominates(&objectives[p]) {
+                domination_count[p] += 1;
+            }
+        }
+        if domination_count[p] == 0 {
+            current.push(p);
+        }
+    }
+
+    while !current.is_empty() {
+        let mut next: Vec<usize> = Vec::new();
+        for &p in current.iter() {
+            for &q in dominated_by[p].iter() {
+                domination_count[q] -= 1;
+                if domination_count[q] == 0 {
+                    next.push(q);
+                }
+            }
+        }
+        fronts.push(current);
+        current = next;
+    }
+
+    fronts
+}
+
+/// Crowding distance for every index in the front
+pub fn crowding_distance(front: &[usize], objectives: &[Objectives]) -> Vec<(usize, f64)> {
+    let mut distances: Vec<(usize, f64)> = front.iter().map(|index| (*index, 0.0)).collect();
+    let size = distances.len();
+    if size < 3 {
+        for distance in distances.iter_mut() {
+            distance.1 = f64::INFINITY;
+        }
+        return distances;
+    }
+
+    for objective in 0..3 {
+        let value = |index: usize| objectives[index].get_values()[objective];
+        distances.sort_by(|a, b| {
+            value(a.0)
+                .partial_cmp(&value(b.0))
+                .unwrap_or(Ordering::Equal)
+        });
+        let min = value(distances[0].0);
+        let max = value(distances[size - 1].0);
+        distances[0].1 = f64::INFINITY;
+        distances[size - 1].1 = f64::INFINITY;
+        if max - min <= 0.0 {
+            continue;
+        }
+        for i in 1..(size - 1) {
+            let gap = value(distances[i + 1].0) - value(distances[i - 1].0);
+            distances[i].1 += gap / (max - min);
+        }
+    }
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_dominated_sort() {
+        let objectives = vec![
+            Objectives::new(10.0, 2.0, 5.0),
+            Objectives::new(8.0, 3.0, 5.0),
+            Objectives::new(12.0, 3.0, 6.0),
+            Objectives::new(10.0, 2.0, 5.0),
+            Objectives::new(14.0, 4.0, 7.0),
+            Objectives::new(9.0, 3.0, 7.0),
+        ];
+        // Equal objectives share a front, and 5 is only dominated by 1
+        let mut fronts = non_dominated_sort(&objectives);
+        for front in fronts.iter_mut() {
+            front.sort();
+        }
+        assert_eq!(fronts, vec![vec![0, 1, 3], vec![2, 5], vec![4]]);
+        assert_eq!(non_dominated_sort(&[]), Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn test_crowding_distance() {
+        let objectives = vec![
+            Objectives::new(0.0, 6.0, 0.0),
+            Objectives::new(1.0, 5.0, 1.0),
+            Objectives::new(4.0, 2.0, 4.0),
+            Objectives::new(6.0, 0.0, 6.0),
+        ];
+        let mut distances = crowding_distance(&[0, 1, 2, 3], &objectives);
+        distances.sort_by_key(|(index, _)| *index);
+        assert_eq!(distances[0], (0, f64::INFINITY));
+        assert!((distances[1].1 - 2.0).abs() < 1e-9);
+        assert!((distances[2].1 - 2.5).abs() < 1e-9);
+        assert_eq!(distances[3], (3, f64::INFINITY));
+
+        // An objective with no spread adds nothing
+        let objectives = vec![
+            Objectives::new(0.0, 1.0, 0.0),
+            Objectives::new(1.0, 1.0, 2.0),
+            Objectives::new(4.0, 1.0, 4.0),
+        ];
+        let distances = crowding_distance(&[0, 1, 2], &objectives);
+        let middle = distances.iter().find(|(index, _)| *index == 1).unwrap();
+        assert!((middle.1 - 2.0).abs() < 1e-9);
+
+        // Fronts with less than three solutions are always kept
+        let distances = crowding_distance(&[2, 0], &objectives);
+        assert!(distances.iter().all(|(_, d)| *d == f64::INFINITY));
+    }
+}
@@ -15,16 +15,19 @@ pub fn load(path: &String) -> Vec<Vec<String>> {
 
     let lines: Vec<Vec<String>> = reader
         .lines()
-        .map(|line| {
-            line.unwrap()
-                .split_whitespace()
-                .map(|word| word.into())
-                .collect()
-        })
+        .map(|line| split_words(&line.unwrap()))
         .collect();
     lines
 }
 
+pub fn load_str(content: &str) -> Vec<Vec<String>> {
+    content.lines().map(split_words).collect()
+}
+
+fn split_words(line: &str) -> Vec<String> {
+    line.split_whitespace().map(|word| word.into()).collect()
+}
+
 pub fn parse_line<T>(line: &Vec<String>, line_number: usize) -> Vec<T>
 where
     T: FromStr,
@@ -0,0 +1,377 @@
+use std::f64;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::problem::{CustomerChange, CustomerKind, Model};
+use crate::solution::Solution;
+
+/// Routes stored as the customers visited by each vehicle, without the
+/// depot at the start and end. Used by the local search based solvers.
+#[derive(Clone)]
+pub struct RoutePlan {
+    pub vehicles: Vec<i32>,
+    pub routes: Vec<Vec<i32>>,
+}
+
+// Smallest change in cost counted as an improvement by the local search
+const IMPROVEMENT_EPSILON: f64 = 1e-9;
+
+/// An insertion of a customer into a route before the given position
+#[derive(Clone, Copy, Debug)]
+pub struct Insertion {
+    pub route: usize,
+    pub position: usize,
+    pub cost: f64,
+}
+
+/// Cost of a vehicle visiting the customers in order, with the same
+/// capacity penalty and vehicle cost as Chromosome::evaluate
+pub fn route_cost<I>(vehicle: i32, customers: I, model: &Model) -> f64
+where
+    I: Iterator<Item = i32>,
+{
+    if model.has_pickups {
+        let customers: Vec<i32> = customers.collect();
+        return mixed_route_cost(vehicle, &customers, model);
+    }
+    let vehicle = vehicle as usize;
+    let mut capacity_left = model.get_demand(vehicle);
+    let mut current_node = vehicle;
+    let mut cost: f64 = 0.0;
+    for customer in customers {
+        let customer = customer as usize;
+        cost += model.get_distance(current_node, customer);
+        capacity_left -= model.get_demand(customer);
+        if capacity_left < 0 {
+            cost += model.capacity_penalty;
+        }
+        current_node = customer;
+    }
+    if current_node != vehicle {
+        cost += model.vehicle_cost;
+    }
+    cost + model.get_distance(current_node, vehicle)
+}
+
+/// Route cost for problems with backhauls or pickup and delivery pairs,
+/// where the load can go up and down along the route
+pub fn mixed_route_cost(vehicle: i32, customers: &[i32], model: &Model) -> f64 {
+    let vehicle = vehicle as usize;
+    let mut current_node = vehicle;
+    let mut cost: f64 = 0.0;
+    for customer in customers.iter() {
+        cost += model.get_distance(current_node, *customer as usize);
+        current_node = *customer as usize;
+    }
+    if !customers.is_empty() {
+        cost += model.vehicle_cost;
+    }
+    let violations = model.load_violations(vehicle, customers);
+    cost + model.get_distance(current_node, vehicle) + violations as f64 * model.capacity_penalty
+}
+
+impl RoutePlan {
+    pub fn from_solution(solution: &Solution) -> RoutePlan {
+        let vehicles = solution.routes.iter().map(|route| route[0]).collect();
+        let routes = solution
+            .routes
+            .iter()
+            .map(|route| route[1..(route.len() - 1)].to_vec())
+            .collect();
+        RoutePlan { vehicles, routes }
+    }
+
+    pub fn to_solution(&self) -> Solution {
+        let routes = self
+            .vehicles
+            .iter()
+            .zip(self.routes.iter())
+            .map(|(vehicle, customers)| {
+                let mut route = Vec::with_capacity(customers.len() + 2);
+                route.push(*vehicle);
+                route.extend(customers.iter());
+                route.push(*vehicle);
+                route
+            })
+            .collect();
+        Solution::new(routes)
+    }
+
+    pub fn route_cost(&self, route: usize, model: &Model) -> f64 {
+        route_cost(
+            self.vehicles[route],
+            self.routes[route].iter().cloned(),
+            model,
+        )
+    }
+
+    pub fn cost(&self, model: &Model) -> f64 {
+        (0..self.routes.len())
+            .map(|route| self.route_cost(route, model))
+            .sum()
+    }
+
+    pub fn customers(&self) -> Vec<i32> {
+        self.routes.iter().flatten().cloned().collect()
+    }
+
+    /// Finds the route and position of a customer
+    pub fn find(&self, customer: i32) -> Option<(usize, usize)> {
+        for (route, customers) in self.routes.iter().enumerate() {
+            if let Some(position) = customers.iter().position(|c| *c == customer) {
+                return Some((route, position));
+            }
+        }
+        None
+    }
+
+    pub fn remove(&mut self, customer: i32) -> Option<(usize, usize)> {
+        let (route, position) = self.find(customer)?;
+        self.routes[route].remove(position);
+        Some((route, position))
+    }
+
+    pub fn insert(&mut self, insertion: &Insertion, customer: i32) {
+        self.routes[insertion.route].insert(insertion.position, customer);
+    }
+
+    /// Cost added to the route by inserting the customer before the position
+    pub fn insertion_cost(
+        &self,
+        route: usize,
+        position: usize,
+        customer: i32,
+        model: &Model,
+    ) -> f64 {
+        let customers = &self.routes[route];
+        let new_route = customers[..position]
+            .iter()
+            .cloned()
+            .chain(Some(customer))
+            .chain(customers[position..].iter().cloned());
+        route_cost(self.vehicles[route], new_route, model) - self.route_cost(route, model)
+    }
+
+    /// The cheapest insertion of the customer in the given route
+    pub fn route_insertion(&self, route: usize, customer: i32, model: &Model) -> Insertion {
+        let mut best = Insertion {
+            route,
+            position: 0,
+            cost: f64::MAX,
+        };
+        for position in 0..=self.routes[route].len() {
+            let cost = self.insertion_cost(route, position, customer, model);
+            if cost < best.cost {
+                best = Insertion {
+                    route,
+                    position,
+                    cost,
+                };
+            }
+        }
+        best
+    }
+
+    /// The cheapest insertion of the customer in every route
+    pub fn route_insertions(&self, customer: i32, model: &Model) -> Vec<Insertion> {
+        (0..self.routes.len())
+            .map(|route| self.route_insertion(route, customer, model))
+            .collect()
+    }
+
+    pub fn best_insertion(&self, customer: i32, model: &Model) -> Insertion {
+        let insertions = self.route_insertions(customer, model);
+        match insertions
+            .into_iter()
+            .min_by(|a, b| a.cost.partial_cmp(&b.cost).unwrap())
+        {
+            Some(insertion) => insertion,
+            None => panic!("No routes to insert customer {} into", customer),
+        }
+    }
+
+    /// Inserts the customer at the cheapest position
+    pub fn insert_best(&mut self, customer: i32, model: &Model) {
+        let insertion = self.best_insertion(customer, model);
+        self.insert(&insertion, customer);
+    }
+
+    /// Indices of the routes visiting at least one customer
+    pub fn used_routes(&self) -> Vec<usize> {
+        (0..self.routes.len())
+            .filter(|route| !self.routes[*route].is_empty())
+            .collect()
+    }
+
+    /// Indices of the vehicles without customers
+    pub fn unused_routes(&self) -> Vec<usize> {
+        (0..self.routes.len())
+            .filter(|route| self.routes[*route].is_empty())
+            .collect()
+    }
+
+    /// Moves all customers of one route to the end of another route
+    pub fn merge_routes(&mut self, from: usize, to: usize) {
+        let customers = std::mem::take(&mut self.routes[from]);
+        self.routes[to].extend(customers);
+    }
+
+    /// Reverses a random segment of a random route
+    pub fn reverse_segment<R: Rng>(&mut self, rng: &mut R) {
+        let routes: Vec<usize> = (0..self.routes.len())
+            .filter(|route| self.routes[*route].len() > 1)
+            .collect();
+        if let Some(route) = routes.choose(rng) {
+            let length = self.routes[*route].len();
+            let start = rng.gen_range(0, length - 1);
+            let end = rng.gen_range(start + 1, length);
+            self.routes[*route][start..=end].reverse();
+        }
+    }
+
+    /// Moves the end of a random route to an unused vehicle, preferring
+    /// vehicles at the same depot
+    pub fn split_route<R: Rng>(&mut self, model: &Model, rng: &mut R) {
+        let routes: Vec<usize> = (0..self.routes.len())
+            .filter(|route| self.routes[*route].len() > 1)
+            .collect();
+        let route = match routes.choose(rng) {
+            Some(route) => *route,
+            None => return,
+        };
+        let depot = model.get_depot(self.vehicles[route] as usize);
+        let unused = self.unused_routes();
+        let same_depot: Vec<usize> = unused
+            .iter()
+            .cloned()
+            .filter(|other| model.get_depot(self.vehicles[*other] as usize) == depot)
+            .collect();
+        let target = match same_depot.choose(rng).or_else(|| unused.choose(rng)) {
+            Some(target) => *target,
+            None => return,
+        };
+        let position = rng.gen_range(1, self.routes[route].len());
+        let tail = self.routes[route].split_off(position);
+        self.routes[target] = tail;
+    }
+
+    /// Merges two random routes into one, freeing a vehicle
+    pub fn merge_random_routes<R: Rng>(&mut self, rng: &mut R) {
+        let used = self.used_routes();
+        if used.len() < 2 {
+            return;
+        }
+        let chosen: Vec<&usize> = used.choose_multiple(rng, 2).collect();
+        self.merge_routes(*chosen[0], *chosen[1]);
+    }
+
+    /// Moves a random route to an unused vehicle at another depot
+    pub fn transfer_route<R: Rng>(&mut self, model: &Model, rng: &mut R) {
+        let route = match self.used_routes().choose(rng) {
+            Some(route) => *route,
+            None => return,
+        };
+        let depot = model.get_depot(self.vehicles[route] as usize);
+        let targets: Vec<usize> = self
+            .unused_routes()
+            .into_iter()
+            .filter(|other| model.get_depot(self.vehicles[*other] as usize) != depot)
+            .collect();
+        if let Some(target) = targets.choose(rng) {
+            self.merge_routes(route, *target);
+        }
+    }
+
+    /// Removes a random customer and inserts it at the cheapest position
+    pub fn reinsert_customer<R: Rng>(&mut self, model: &Model, rng: &mut R) {
+        if let Some(customer) = self.customers().choose(rng) {
+            self.remove(*customer);
+            self.insert_best(*customer, model);
+        }
+    }
+
+    /// Moves every delivery that is not after its pickup on the same route
+    /// to the cheapest position after the pickup
+    pub fn repair_pairs(&mut self, model: &Model) {
+        for customer in self.customers() {
+            let pickup = match model.get_kind(customer as usize) {
+                CustomerKind::Delivery(pickup) => pickup,
+                _ => continue,
+            };
+            let (pickup_route, pickup_position) = self.find(pickup).unwrap();
+            let (route, position) = self.find(customer).unwrap();
+            if route == pickup_route && position > pickup_position {
+                continue;
+            }
+            self.remove(customer);
+            let (route, pickup_position) = self.find(pickup).unwrap();
+            let mut best = Insertion {
+                route,
+                position: pickup_position + 1,
+                cost: f64::MAX,
+            };
+            for position in (pickup_position + 1)..=self.routes[route].len() {
+                let cost = self.insertion_cost(route, position, customer, model);
+                if cost < best.cost {
+                    best.position = position;
+                    best.cost = cost;
+                }
+            }
+            self.insert(&best, customer);
+        }
+    }
+
+    /// Renumbers the routes after a customer is added or removed, and
+    /// inserts an added customer at the cheapest position
+    pub fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        for vehicle in self.vehicles.iter_mut() {
+            *vehicle = change.renumber(*vehicle).unwrap();
+        }
+        for route in self.routes.iter_mut() {
+            *route = route
+                .iter()
+                .filter_map(|customer| change.renumber(*customer))
+                .collect();
+        }
+        if let CustomerChange::Added(customer) = change {
+            self.insert_best(customer, model);
+        }
+    }
+
+    /// Relocates customers and reverses route segments until no move
+    /// improves the cost
+    pub fn local_search(&mut self, model: &Model) {
+        let mut improved = true;
+        while improved {
+            improved = false;
+
+            for customer in self.customers() {
+                let (route, position) = self.remove(customer).unwrap();
+                let saving = self.insertion_cost(route, position, customer, model);
+                let insertion = self.best_insertion(customer, model);
+                if insertion.cost < saving - IMPROVEMENT_EPSILON {
+                    self.insert(&insertion, customer);
+                    improved = true;
+                } else {
+                    self.routes[route].insert(position, customer);
+                }
+            }
+
+            for route in 0..self.routes.len() {
+                let length = self.routes[route].len();
+                for start in 0..length {
+                    for end in (start + 1)..length {
+                        let cost = self.route_cost(route, model);
+                        self.routes[route][start..=end].reverse();
+                        if self.route_cost(route, model) < cost - IMPROVEMENT_EPSILON {
+                            improved = true;
+                        } else {
+                            self.routes[route][start..=end].reverse();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
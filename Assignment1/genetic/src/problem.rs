@@ -1,15 +1,24 @@
 use std::collections::HashMap;
 use std::f64;
+use std::fs;
 use std::hash::{Hash, Hasher};
 use std::i32;
+use std::str::FromStr;
 
 use rayon::prelude::*;
 
 use crate::config::Config;
+use crate::cvrplib;
+use crate::distance::{DistanceMatrix, Rounding};
 use crate::heuristic;
+use crate::pareto::ParetoSimulation;
 use crate::parser;
-use crate::simulation::{Chromosome, Encode, Simulation};
+use crate::plan::RoutePlan;
+use crate::random;
+use crate::schedule::Schedule;
+use crate::simulation::{Chromosome, Encode, CAPACITY_PENALTY};
 use crate::solution::{OptimalSolution, Solution};
+use crate::solver::{self, Solver};
 use crate::utils::Pos;
 
 pub struct Customer {
@@ -17,6 +26,20 @@ pub struct Customer {
     pub pos: Pos,
     service_time: Option<i32>,
     demand: i32,
+    kind: CustomerKind,
+}
+
+/// How visiting a customer changes the load of the vehicle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CustomerKind {
+    // Delivers goods loaded at the depot
+    Linehaul,
+    // Picks up goods brought back to the depot
+    Backhaul,
+    // Picks up goods for the paired delivery customer
+    Pickup(i32),
+    // Delivers goods from the paired pickup customer
+    Delivery(i32),
 }
 
 #[derive(Eq, PartialEq)]
@@ -24,6 +47,8 @@ pub struct Depot {
     pub capacity: i32,
     pub number: i32,
     pub pos: Pos,
+    pub max_vehicles: i32,
+    pub max_duration: Option<i32>,
 }
 
 impl Hash for Depot {
@@ -57,6 +82,11 @@ pub struct Model {
     pub num_nodes: usize,
     pub distances: Vec<f64>,
     pub capacities: Vec<i32>,
+    pub vehicle_cost: f64, // Added for every vehicle visiting at least one customer
+    pub capacity_penalty: f64,
+    pub depots: Vec<i32>, // Depot of every vehicle, 0 for customers
+    pub kinds: Vec<CustomerKind>,
+    pub has_pickups: bool, // Any backhaul or pickup customers
 }
 
 impl Model {
@@ -68,20 +98,115 @@ impl Model {
     pub fn get_demand(&self, node: usize) -> i32 {
         self.capacities[node - 1]
     }
+
+    pub fn get_depot(&self, vehicle: usize) -> i32 {
+        self.depots[vehicle - 1]
+    }
+
+    pub fn get_kind(&self, node: usize) -> CustomerKind {
+        self.kinds[node - 1]
+    }
+
+    /// Load when leaving the depot. Linehaul goods are loaded at the depot.
+    pub fn initial_load(&self, customers: &[i32]) -> i32 {
+        customers
+            .iter()
+            .filter(|customer| self.get_kind(**customer as usize) == CustomerKind::Linehaul)
+            .map(|customer| self.get_demand(*customer as usize))
+            .sum()
+    }
+
+    /// Change in the load of the vehicle when visiting the customer
+    pub fn load_change(&self, customer: usize) -> i32 {
+        match self.get_kind(customer) {
+            CustomerKind::Linehaul | CustomerKind::Delivery(_) => -self.get_demand(customer),
+            CustomerKind::Backhaul | CustomerKind::Pickup(_) => self.get_demand(customer),
+        }
+    }
+
+    /// Counts the stops where the load is above the vehicle capacity,
+    /// including the start at the depot, and the deliveries visited
+    /// before their pickup or without it on the route
+    pub fn load_violations(&self, vehicle: usize, customers: &[i32]) -> usize {
+        let capacity = self.get_demand(vehicle);
+        let mut load = self.initial_load(customers);
+        let mut violations = if load > capacity { 1 } else { 0 };
+        for (i, customer) in customers.iter().enumerate() {
+            load += self.load_change(*customer as usize);
+            if let CustomerKind::Delivery(pickup) = self.get_kind(*customer as usize) {
+                if !customers[..i].contains(&pickup) {
+                    violations += 1;
+                }
+            }
+            if load > capacity {
+                violations += 1;
+            }
+        }
+        violations
+    }
 }
 
 pub struct Problem {
-    pub max_vehicles: i32,  // Maximum number of vehicles available for each depot
+    pub max_vehicles: i32,  // Default number of vehicles available for each depot
     pub num_customers: i32, // Total number of customers
     pub num_depots: i32,    // Number of depots
+    pub vehicle_fixed_cost: f64,
+    pub minimize_fleet: bool,
     customers: Vec<Customer>,
     depots: Vec<Depot>,
     pub vehicles: Vec<Vehicle>,
-    pub simulation: Simulation,
+    pub solver: Box<dyn Solver>,
+    pub pareto: Option<ParetoSimulation>,
+    distance_matrix: DistanceMatrix,
+    // False when the distances were read from a file or given as a matrix
+    euclidean_distances: bool,
+    distance_rounding: Rounding,
     optimal_solution: Option<OptimalSolution>,
     model: Option<Model>,
 }
 
+/// How a solution loaded from WARM_START_PATH is used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WarmStart {
+    // Added to the initial population as it is
+    Seed,
+    // Improved by local search before it is added
+    Polish,
+}
+
+impl FromStr for WarmStart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "seed" => Ok(WarmStart::Seed),
+            "polish" | "local_search" => Ok(WarmStart::Polish),
+            _ => Err(format!("Unknown warm start mode: {}", s)),
+        }
+    }
+}
+
+/// A customer added to or removed from a problem during a run.
+/// Customers are numbered 1..=N followed by the vehicles, so every node
+/// after the changed customer is renumbered.
+#[derive(Clone, Copy, Debug)]
+pub enum CustomerChange {
+    Added(i32),
+    Removed(i32),
+}
+
+impl CustomerChange {
+    /// The new number of a node, or None if it was removed
+    pub fn renumber(&self, node: i32) -> Option<i32> {
+        match *self {
+            CustomerChange::Added(number) if node >= number => Some(node + 1),
+            CustomerChange::Removed(number) if node == number => None,
+            CustomerChange::Removed(number) if node > number => Some(node - 1),
+            _ => Some(node),
+        }
+    }
+}
+
 impl Clone for Customer {
     fn clone(&self) -> Self {
         Customer {
@@ -89,15 +214,54 @@ impl Clone for Customer {
             pos: self.pos.clone(),
             service_time: self.service_time,
             demand: self.demand,
+            kind: self.kind,
         }
     }
 }
 
+impl Customer {
+    /// Customers with a negative demand are backhauls
+    pub fn new(number: i32, pos: Pos, service_time: Option<i32>, demand: i32) -> Customer {
+        let kind = if demand < 0 {
+            CustomerKind::Backhaul
+        } else {
+            CustomerKind::Linehaul
+        };
+        Customer {
+            number,
+            pos,
+            service_time,
+            demand: demand.abs(),
+            kind,
+        }
+    }
+
+    pub fn get_demand(&self) -> i32 {
+        self.demand
+    }
+
+    pub fn get_service_time(&self) -> i32 {
+        self.service_time.unwrap_or(0)
+    }
+
+    pub fn get_kind(&self) -> CustomerKind {
+        self.kind
+    }
+}
+
 impl Problem {
     pub fn new(config: &Config) -> Problem {
         let path = config.problem_path.clone();
+        if path.ends_with(".vrp") {
+            return cvrplib::load(&path, config);
+        }
         let lines = parser::load(&path);
+        Problem::from_lines(&lines, config)
+    }
 
+    /// Creates a problem from the lines of a problem file,
+    /// split into words
+    pub fn from_lines(lines: &[Vec<String>], config: &Config) -> Problem {
         // Parse problem global settings
         let first_line = parser::parse_line::<i32>(&lines[0], 1);
         let max_vehicles = first_line[0];
@@ -115,7 +279,7 @@ impl Problem {
             .enumerate()
             .map(|(i, line)| {
                 let info_line = parser::parse_line::<i32>(line, i);
-                let _max_duration = match info_line[0] {
+                let max_duration = match info_line[0] {
                     0 => None,
                     val => Some(val),
                 };
@@ -130,6 +294,8 @@ impl Problem {
                     capacity,
                     number,
                     pos,
+                    max_vehicles,
+                    max_duration,
                 }
             })
             .collect();
@@ -154,19 +320,59 @@ impl Problem {
                     val => Some(val),
                 };
                 let demand = line[4];
-                Customer {
-                    number,
-                    pos,
-                    service_time,
-                    demand,
-                }
+                Customer::new(number, pos, service_time, demand)
             })
             .collect();
 
+        Problem::from_parts(max_vehicles, depots, customers, None, config)
+    }
+
+    /// Creates a problem from parsed depots and customers. Customers must be
+    /// numbered 1..=N and depots N+1..=N+D. Euclidean distances are used when
+    /// no distance matrix is given.
+    pub fn from_parts(
+        max_vehicles: i32,
+        mut depots: Vec<Depot>,
+        mut customers: Vec<Customer>,
+        distance_matrix: Option<DistanceMatrix>,
+        config: &Config,
+    ) -> Problem {
+        let num_customers = customers.len() as i32;
+        let num_depots = depots.len() as i32;
+
+        if !config.depot_vehicle_limits.is_empty() {
+            let limits = parse_vehicle_limits(&config.depot_vehicle_limits);
+            if limits.len() != depots.len() {
+                panic!(
+                    "Got {} depot vehicle limits, expected {}",
+                    limits.len(),
+                    depots.len()
+                );
+            }
+            for (depot, limit) in depots.iter_mut().zip(limits) {
+                depot.max_vehicles = limit;
+            }
+        }
+        if !config.pickup_delivery_path.is_empty() {
+            let lines = parser::load(&config.pickup_delivery_path);
+            let pairs: Vec<(i32, i32)> = lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let line = parser::parse_line::<i32>(line, i);
+                    (line[0], line[1])
+                })
+                .collect();
+            pair_customers(&mut customers, &pairs);
+        }
+        if depots.iter().all(|depot| depot.max_vehicles == 0) {
+            panic!("No vehicles available at any depot");
+        }
+
         let mut vehicles: Vec<Vehicle> = Vec::new();
         let mut vehicle_number: i32 = num_customers + 1;
         for depot in depots.iter() {
-            for _ in 0..max_vehicles {
+            for _ in 0..depot.max_vehicles {
                 vehicles.push(Vehicle {
                     number: vehicle_number,
                     depot: depot.number,
@@ -176,14 +382,45 @@ impl Problem {
             }
         }
 
+        let euclidean_distances =
+            config.distance_matrix_path.is_empty() && distance_matrix.is_none();
+        let mut distance_matrix = if !config.distance_matrix_path.is_empty() {
+            DistanceMatrix::from_file(&config.distance_matrix_path)
+        } else if let Some(distance_matrix) = distance_matrix {
+            distance_matrix
+        } else {
+            let mut positions: Vec<Pos> = vec![Pos { x: 0, y: 0 }; customers.len() + depots.len()];
+            for customer in customers.iter() {
+                positions[customer.number as usize - 1] = customer.pos.clone();
+            }
+            for depot in depots.iter() {
+                positions[depot.number as usize - 1] = depot.pos.clone();
+            }
+            DistanceMatrix::from_positions(&positions)
+        };
+        if distance_matrix.dimension != (num_customers + num_depots) as usize {
+            panic!(
+                "Distance matrix has dimension {}, expected {}",
+                distance_matrix.dimension,
+                num_customers + num_depots
+            );
+        }
+        distance_matrix.round(config.distance_rounding);
+
         let mut problem = Problem {
             max_vehicles,
             num_customers,
             num_depots,
+            vehicle_fixed_cost: config.vehicle_fixed_cost,
+            minimize_fleet: config.minimize_fleet,
             depots,
             customers,
             vehicles,
-            simulation: Simulation::new(config),
+            solver: solver::create(config),
+            pareto: None,
+            distance_matrix,
+            euclidean_distances,
+            distance_rounding: config.distance_rounding,
             optimal_solution: None,
             model: None,
         };
@@ -211,33 +448,60 @@ impl Problem {
 
     pub fn create_model(&mut self) {
         let num_nodes = self.vehicles.len() + self.customers.len();
+        let distances = self.calculate_distances();
+        let mut vehicle_cost = self.vehicle_fixed_cost;
+        let mut capacity_penalty = CAPACITY_PENALTY;
+        if self.minimize_fleet {
+            // A route never has more edges than there are nodes, so one
+            // vehicle costs more than any total distance. Overloading a
+            // vehicle must still cost more than the whole fleet.
+            let max_distance = distances.iter().cloned().fold(0.0, f64::max);
+            vehicle_cost += max_distance * num_nodes as f64;
+            capacity_penalty += vehicle_cost * self.vehicles.len() as f64;
+        }
         self.model = Some(Model {
             num_nodes: num_nodes,
-            distances: self.calculate_distances(),
+            distances,
             capacities: self.calculate_capacities(),
+            vehicle_cost,
+            capacity_penalty,
+            depots: self.calculate_depots(),
+            kinds: self.calculate_kinds(),
+            has_pickups: self
+                .customers
+                .iter()
+                .any(|customer| customer.kind != CustomerKind::Linehaul),
         });
     }
 
+    pub fn get_model(&self) -> &Model {
+        match self.model.as_ref() {
+            Some(model) => model,
+            None => {
+                panic!("Model not created!");
+            }
+        }
+    }
+
     pub fn calculate_distances(&self) -> Vec<f64> {
         let num_nodes = self.customers.len() + self.vehicles.len();
         let mut distances: Vec<f64> = vec![0.0; num_nodes * num_nodes];
 
-        let mut positions: HashMap<usize, Pos> = HashMap::new();
+        // Index in the distance matrix for every node in the model
+        let mut indices: Vec<usize> = vec![0; num_nodes];
         for customer in self.customers.iter() {
-            positions.insert(customer.number as usize, customer.pos.clone());
+            indices[customer.number as usize - 1] = customer.number as usize - 1;
         }
 
         for vehicle in self.vehicles.iter() {
             let depot = vehicle.get_depot(&self.depots);
-            let pos = depot.pos.clone();
-            positions.insert(vehicle.number as usize, pos);
+            indices[vehicle.number as usize - 1] = depot.number as usize - 1;
         }
 
-        for (key1, pos1) in positions.iter() {
-            for (key2, pos2) in positions.iter() {
-                let index = (key1 - 1) * num_nodes + (key2 - 1);
-                let distance = pos1.distance_to(pos2);
-                distances[index] = distance;
+        for (from, from_index) in indices.iter().enumerate() {
+            for (to, to_index) in indices.iter().enumerate() {
+                let index = from * num_nodes + to;
+                distances[index] = self.distance_matrix.get(*from_index, *to_index);
             }
         }
 
@@ -245,7 +509,7 @@ impl Problem {
     }
 
     pub fn get_solution(&self) -> Solution {
-        let mut solution = self.simulation.get_best_solution();
+        let mut solution = self.solver.get_best_solution();
         // let model = self.model.as_ref().unwrap();
         // solution.evaluate(model);
 
@@ -294,6 +558,44 @@ impl Problem {
         capacities
     }
 
+    pub fn calculate_depots(&self) -> Vec<i32> {
+        let num_nodes = self.customers.len() + self.vehicles.len();
+        let mut depots: Vec<i32> = vec![0; num_nodes];
+        for v in self.vehicles.iter() {
+            depots[v.number as usize - 1] = v.depot;
+        }
+
+        depots
+    }
+
+    pub fn calculate_kinds(&self) -> Vec<CustomerKind> {
+        let num_nodes = self.customers.len() + self.vehicles.len();
+        let mut kinds: Vec<CustomerKind> = vec![CustomerKind::Linehaul; num_nodes];
+        for c in self.customers.iter() {
+            kinds[c.number as usize - 1] = c.kind;
+        }
+
+        kinds
+    }
+
+    pub fn get_customer(&self, number: i32) -> &Customer {
+        match self
+            .customers
+            .iter()
+            .find(|customer| customer.number == number)
+        {
+            Some(customer) => customer,
+            None => panic!("No customer with number {}", number),
+        }
+    }
+
+    pub fn get_depot(&self, number: i32) -> &Depot {
+        match self.depots.iter().find(|depot| depot.number == number) {
+            Some(depot) => depot,
+            None => panic!("No depot with number {}", number),
+        }
+    }
+
     pub fn get_customers(&self) -> HashMap<i32, (i32, i32)> {
         let mut hashmap = HashMap::new();
         for customer in self.customers.iter() {
@@ -356,38 +658,136 @@ impl Problem {
             println!("Generating population");
         }
         let model = self.model.as_ref().unwrap();
-        while self.simulation.population.size() < config.population_size {
-            let new_chromosomes: Vec<Chromosome> = (0..config.population_gen_step)
+        // The genetic population is used as the initial multi-objective population
+        let size = if config.multi_objective {
+            config.population_size
+        } else {
+            self.solver.initial_size(config)
+        };
+        let mut chromosomes: Vec<Chromosome> = Vec::with_capacity(size);
+        while chromosomes.len() < size {
+            let step = config.population_gen_step.min(size - chromosomes.len());
+            let new_chromosomes: Vec<Chromosome> = random::task_seeds(step)
                 .into_par_iter()
-                .map(|_| {
-                    let route = heuristic::savings_init(config, &model, &self);
-                    Solution::new(route).encode()
-                    // heuristic::random_init(&model, &self)
+                .map(|seed| {
+                    random::with_seed(seed, || {
+                        let route = heuristic::savings_init(config, model, self);
+                        let chromosome = Solution::new(route).encode();
+                        if model.has_pickups {
+                            chromosome.repair(model)
+                        } else {
+                            chromosome
+                        }
+                        // heuristic::random_init(&model, &self)
+                    })
                 })
                 .collect();
 
-            self.simulation
-                .population
-                .chromosomes
-                .par_extend(new_chromosomes);
+            chromosomes.par_extend(new_chromosomes);
 
             if config.verbose {
-                let generated = self.simulation.population.size();
-                println!(
-                    "Generated {} of {} individuals",
-                    generated, config.population_size
+                println!("Generated {} of {} individuals", chromosomes.len(), size);
+            }
+        }
+
+        if !config.warm_start_path.is_empty() {
+            let mut solution = self.load_solution(&config.warm_start_path);
+            if config.warm_start_mode == WarmStart::Polish {
+                solution = self.polish(&solution);
+            }
+            chromosomes[0] = solution.encode();
+        }
+
+        if config.multi_objective {
+            self.pareto = Some(ParetoSimulation::new(chromosomes.clone(), model));
+        }
+        self.solver.seed(chromosomes, model);
+    }
+
+    /// Loads a solution in the .res format, either written by this program
+    /// or published with the problem. Vehicles are numbered per depot.
+    pub fn load_solution(&self, path: &str) -> Solution {
+        let mut solution = OptimalSolution::new(path.to_string()).get_solution(self);
+        // Some published solutions include the depot as 0 at both ends
+        for route in solution.routes.iter_mut() {
+            route.retain(|stop| *stop != 0);
+        }
+
+        let mut visits = vec![0; self.num_customers as usize];
+        for route in solution.routes.iter() {
+            for customer in route[1..(route.len() - 1)].iter() {
+                if *customer < 1 || *customer > self.num_customers {
+                    panic!("Solution {} visits unknown customer {}", path, customer);
+                }
+                visits[*customer as usize - 1] += 1;
+            }
+        }
+        for (i, count) in visits.iter().enumerate() {
+            if *count != 1 {
+                panic!(
+                    "Solution {} visits customer {} {} times",
+                    path,
+                    i + 1,
+                    count
                 );
             }
         }
-        self.simulation.evaluate(model);
+
+        solution.evaluate(self.get_model());
+        solution
+    }
+
+    /// Improves the solution with local search
+    pub fn polish(&self, solution: &Solution) -> Solution {
+        let model = self.get_model();
+        let mut plan = RoutePlan::from_solution(solution);
+        plan.local_search(model);
+        let mut solution = plan.to_solution();
+        solution.evaluate(model);
+        solution
+    }
+
+    /// Polishes the solution in WARM_START_PATH and writes the result to
+    /// SOLUTION_PATH
+    pub fn polish_solution(&self, config: &Config) -> Solution {
+        let solution = self.load_solution(&config.warm_start_path);
+        let mut polished = self.polish(&solution);
+        if config.verbose {
+            println!(
+                "Improved solution from {:.2} to {:.2}",
+                solution.score(),
+                polished.score()
+            );
+        }
+        if !config.solution_path.is_empty() {
+            polished.write_to_file(config, self, self.get_model());
+        }
+        polished
+    }
+
+    /// Writes the best solution found so far to SOLUTION_PATH, and its
+    /// schedule to SCHEDULE_PATH if set
+    pub fn write_solution(&self, config: &Config) {
+        let mut solution = self.solver.get_best_solution();
+        solution.write_to_file(config, self, self.get_model());
+        if !config.schedule_path.is_empty() {
+            self.write_schedule(config);
+        }
+    }
+
+    /// Writes the per-stop schedule of the best solution to SCHEDULE_PATH,
+    /// as JSON if the path ends with .json and as a table otherwise
+    pub fn write_schedule(&self, config: &Config) {
+        let solution = self.solver.get_best_solution();
+        Schedule::new(&solution, self).write_to_path(&config.schedule_path);
     }
 
     pub fn simulate(&mut self, config: &Config) -> Solution {
         let model = self.model.as_ref().unwrap();
-        let mut solution: Solution = self.simulation.get_best_solution();
+        let mut solution: Solution = self.solver.get_best_solution();
         for _ in 0..config.draw_rate {
-            self.simulation.run(model, config);
-            solution = self.simulation.get_best_solution();
+            self.solver.run(model, config);
+            solution = self.solver.get_best_solution();
         }
         solution
         /*
@@ -429,6 +829,148 @@ impl Problem {
         */
     }
 
+    /// Adds a customer while the problem is being solved and inserts it in
+    /// every solution at the cheapest position. Distances to the new customer
+    /// are Euclidean, so this fails when the distances are not. Returns the
+    /// number of the new customer.
+    pub fn add_customer(
+        &mut self,
+        pos: Pos,
+        demand: i32,
+        service_time: Option<i32>,
+    ) -> Result<i32, String> {
+        if !self.euclidean_distances {
+            return Err(String::from(
+                "Cannot add customers when the distance matrix is not Euclidean",
+            ));
+        }
+        let number = self.num_customers + 1;
+        let rounding = self.distance_rounding;
+        let positions: Vec<Pos> = self
+            .customers
+            .iter()
+            .map(|customer| customer.pos.clone())
+            .chain(self.depots.iter().map(|depot| depot.pos.clone()))
+            .collect();
+        self.distance_matrix
+            .insert_node(number as usize - 1, |node| {
+                let distance = rounding.apply(pos.distance_to(&positions[node]));
+                (distance, distance)
+            });
+        // Renumber before pushing, so the new customer keeps its number
+        let change = CustomerChange::Added(number);
+        self.renumber_nodes(change);
+        self.customers
+            .push(Customer::new(number, pos, service_time, demand));
+        self.num_customers += 1;
+
+        self.update_solvers(change);
+        Ok(number)
+    }
+
+    /// Removes a customer while the problem is being solved. Customers with
+    /// a higher number are renumbered.
+    pub fn remove_customer(&mut self, number: i32) -> Result<(), String> {
+        if number < 1 || number > self.num_customers {
+            return Err(format!("No customer with number {}", number));
+        }
+        self.distance_matrix.remove_node(number as usize - 1);
+        self.customers.remove(number as usize - 1);
+        self.num_customers -= 1;
+
+        let change = CustomerChange::Removed(number);
+        self.renumber_nodes(change);
+        self.update_solvers(change);
+        Ok(())
+    }
+
+    /// Removes the customer at the position. Unlike the number, the position
+    /// does not change when other customers are removed. Returns the number
+    /// the customer had.
+    pub fn remove_customer_at(&mut self, pos: &Pos) -> Result<i32, String> {
+        let number = match self.customers.iter().find(|customer| customer.pos == *pos) {
+            Some(customer) => customer.number,
+            None => return Err(format!("No customer at ({}, {})", pos.x, pos.y)),
+        };
+        self.remove_customer(number)?;
+        Ok(number)
+    }
+
+    fn renumber_nodes(&mut self, change: CustomerChange) {
+        let renumber = |node: i32| match change.renumber(node) {
+            Some(node) => node,
+            None => panic!("Node {} was removed", node),
+        };
+        for customer in self.customers.iter_mut() {
+            customer.number = renumber(customer.number);
+            // A pickup or delivery without its pair becomes a backhaul or linehaul
+            customer.kind = match customer.kind {
+                CustomerKind::Pickup(delivery) => match change.renumber(delivery) {
+                    Some(delivery) => CustomerKind::Pickup(delivery),
+                    None => CustomerKind::Backhaul,
+                },
+                CustomerKind::Delivery(pickup) => match change.renumber(pickup) {
+                    Some(pickup) => CustomerKind::Delivery(pickup),
+                    None => CustomerKind::Linehaul,
+                },
+                kind => kind,
+            };
+        }
+        for depot in self.depots.iter_mut() {
+            depot.number = renumber(depot.number);
+        }
+        for vehicle in self.vehicles.iter_mut() {
+            vehicle.number = renumber(vehicle.number);
+            vehicle.depot = renumber(vehicle.depot);
+        }
+        // The optimal solution is for the original customers
+        self.optimal_solution = None;
+    }
+
+    fn update_solvers(&mut self, change: CustomerChange) {
+        self.create_model();
+        let model = self.model.as_ref().unwrap();
+        self.solver.update_customers(change, model);
+        if let Some(pareto) = self.pareto.as_mut() {
+            pareto.update_customers(change, model);
+        }
+    }
+
+    pub fn simulate_pareto(&mut self, config: &Config) -> Vec<Solution> {
+        let model = self.model.as_ref().unwrap();
+        let pareto = match self.pareto.as_mut() {
+            Some(pareto) => pareto,
+            None => {
+                panic!("Multi-objective population not generated!");
+            }
+        };
+        for _ in 0..config.draw_rate {
+            pareto.run(model, config);
+        }
+        pareto.get_pareto_set()
+    }
+
+    /// Writes every solution in the Pareto set to its own .res file
+    pub fn write_pareto_set(&self, config: &Config) {
+        let model = self.model.as_ref().unwrap();
+        let pareto = match self.pareto.as_ref() {
+            Some(pareto) => pareto,
+            None => {
+                panic!("Multi-objective population not generated!");
+            }
+        };
+        if let Err(err) = fs::create_dir_all(&config.pareto_path) {
+            panic!(
+                "Failed to create folder {}. Error: {}",
+                config.pareto_path, err
+            );
+        }
+        for (i, mut solution) in pareto.get_pareto_set().into_iter().enumerate() {
+            let file_path = format!("{}/{}.res", config.pareto_path, i + 1);
+            solution.write_to_path(&file_path, self, model);
+        }
+    }
+
     pub fn map_customers_to_depot(&self) -> HashMap<&Depot, Vec<Customer>> {
         // Assigns customers to the closest depot
         let mut depot_map: HashMap<&Depot, Vec<Customer>> = HashMap::new();
@@ -436,8 +978,11 @@ impl Problem {
         for customer in self.customers.iter() {
             let mut distance = f64::MAX;
             let mut closest_depot: Option<&Depot> = None;
-            for depot in self.depots.iter() {
-                let new_distance = customer.pos.distance_to(&depot.pos);
+            for depot in self.depots.iter().filter(|depot| depot.max_vehicles > 0) {
+                let customer_index = customer.number as usize - 1;
+                let depot_index = depot.number as usize - 1;
+                let new_distance = self.distance_matrix.get(depot_index, customer_index)
+                    + self.distance_matrix.get(customer_index, depot_index);
                 if new_distance < distance {
                     distance = new_distance;
                     closest_depot = Some(depot);
@@ -460,3 +1005,169 @@ impl Problem {
         return depot_map;
     }
 }
+
+/// Parses a comma separated list with the number of vehicles at each depot
+fn parse_vehicle_limits(limits: &str) -> Vec<i32> {
+    limits
+        .split(',')
+        .map(|limit| match limit.trim().parse::<i32>() {
+            Ok(limit) if limit >= 0 => limit,
+            _ => panic!("Invalid depot vehicle limit: {}", limit),
+        })
+        .collect()
+}
+
+/// Pairs the customers as (pickup, delivery). Both customers must have
+/// the same demand.
+fn pair_customers(customers: &mut [Customer], pairs: &[(i32, i32)]) {
+    let num_customers = customers.len() as i32;
+    for (pickup, delivery) in pairs.iter().cloned() {
+        for customer in [pickup, delivery].iter() {
+            if *customer < 1 || *customer > num_customers {
+                panic!("No customer with number {} to pair", customer);
+            }
+            if let CustomerKind::Pickup(_) | CustomerKind::Delivery(_) =
+                customers[*customer as usize - 1].kind
+            {
+                panic!("Customer {} is paired more than once", customer);
+            }
+        }
+        if pickup == delivery {
+            panic!("Customer {} cannot be paired with itself", pickup);
+        }
+        let (pickup_demand, delivery_demand) = (
+            customers[pickup as usize - 1].demand,
+            customers[delivery as usize - 1].demand,
+        );
+        if pickup_demand != delivery_demand {
+            panic!(
+                "Pickup {} has demand {} but delivery {} has demand {}",
+                pickup, pickup_demand, delivery, delivery_demand
+            );
+        }
+        customers[pickup as usize - 1].kind = CustomerKind::Pickup(delivery);
+        customers[delivery as usize - 1].kind = CustomerKind::Delivery(pickup);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    use crate::generator::InstanceGenerator;
+    use crate::simulation::Decode;
+
+    /// Problem with customer 1 picking up for customer 2 and customer 3
+    /// as a backhaul
+    fn pickup_delivery_problem() -> Problem {
+        let config = Config::default();
+        let generator = InstanceGenerator::new(6, 1, 5);
+        let lines = parser::load_str(&generator.generate());
+        let mut problem = Problem::from_lines(&lines, &config);
+        for customer in problem.customers.iter_mut() {
+            customer.demand = 5;
+        }
+        for vehicle in problem.vehicles.iter_mut() {
+            vehicle.capacity = 10;
+        }
+        pair_customers(&mut problem.customers, &[(1, 2)]);
+        problem.customers[2].kind = CustomerKind::Backhaul;
+        problem.create_model();
+        problem
+    }
+
+    #[test]
+    fn test_load_violations() {
+        let problem = pickup_delivery_problem();
+        let model = problem.get_model();
+        let vehicle = problem.vehicles[0].number as usize;
+        assert!(model.has_pickups);
+
+        assert_eq!(model.load_violations(vehicle, &[1, 2]), 0);
+        // Delivery before its pickup
+        assert_eq!(model.load_violations(vehicle, &[2, 1]), 1);
+        // Delivery without its pickup on the route
+        assert_eq!(model.load_violations(vehicle, &[2]), 1);
+        // Linehaul goods fill the vehicle when leaving the depot
+        assert_eq!(model.load_violations(vehicle, &[4, 5, 1, 2, 3]), 0);
+        assert_eq!(model.load_violations(vehicle, &[1, 4, 5, 2]), 1);
+        assert_eq!(model.load_violations(vehicle, &[3, 4, 5]), 1);
+        assert_eq!(model.load_violations(vehicle, &[4, 5, 6]), 1);
+    }
+
+    #[test]
+    fn test_repair_pairs() {
+        let problem = pickup_delivery_problem();
+        let model = problem.get_model();
+        let vehicles: Vec<i32> = problem.vehicles.iter().map(|v| v.number).collect();
+        let mut routes = vec![vec![]; vehicles.len()];
+        routes[0] = vec![2, 4, 3];
+        routes[1] = vec![5, 1, 6];
+        let plan = RoutePlan { vehicles, routes };
+
+        let chromosome = plan.to_solution().encode().repair(model);
+        let repaired = RoutePlan::from_solution(&chromosome.decode());
+        let (pickup_route, pickup_position) = repaired.find(1).unwrap();
+        let (route, position) = repaired.find(2).unwrap();
+        assert_eq!(route, pickup_route);
+        assert!(position > pickup_position);
+        assert_eq!(repaired.customers().len(), 6);
+
+        let mut chromosome = chromosome;
+        assert!(chromosome.evaluate(model) < model.capacity_penalty);
+    }
+
+    #[test]
+    fn test_add_customer() {
+        let config = Config::default();
+        let generator = InstanceGenerator::new(6, 1, 5);
+        let lines = parser::load_str(&generator.generate());
+        let mut problem = Problem::from_lines(&lines, &config);
+        let first = problem.customers[0].pos.clone();
+        let pos = Pos { x: 10, y: 10 };
+
+        let number = problem.add_customer(pos.clone(), 4, None).unwrap();
+        assert_eq!(number, 7);
+        let customers = problem.get_customers();
+        let mut keys: Vec<i32> = customers.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, (1..=7).collect::<Vec<i32>>());
+        assert_eq!(customers[&7], (10, 10));
+        assert!(problem.vehicles.iter().all(|vehicle| vehicle.number > 7));
+
+        let model = problem.get_model();
+        assert_eq!(model.get_demand(7), 4);
+        assert_eq!(model.get_distance(7, 1), pos.distance_to(&first));
+        assert_eq!(model.get_distance(1, 7), pos.distance_to(&first));
+
+        problem.remove_customer(7).unwrap();
+        assert_eq!(problem.get_customers().len(), 6);
+        assert!(problem.vehicles.iter().all(|vehicle| vehicle.number > 6));
+        assert!(problem.remove_customer(7).is_err());
+
+        // Removing by position is not affected by the renumbering
+        let second = problem.customers[1].pos.clone();
+        let third = problem.customers[2].pos.clone();
+        let fourth = problem.customers[3].pos.clone();
+        assert_eq!(problem.remove_customer_at(&second), Ok(2));
+        assert_eq!(problem.remove_customer_at(&third), Ok(2));
+        assert_eq!(problem.customers[1].pos, fourth);
+        assert!(problem.remove_customer_at(&third).is_err());
+    }
+
+    #[test]
+    fn test_add_customer_with_distance_matrix() {
+        let mut config = Config::default();
+        let generator = InstanceGenerator::new(3, 1, 5);
+        let lines = parser::load_str(&generator.generate());
+        let path = env::temp_dir().join(format!("genetic-matrix-{}.csv", process::id()));
+        fs::write(&path, "0,1,2,3\n1,0,1,2\n2,1,0,1\n3,2,1,0\n").unwrap();
+        config.distance_matrix_path = path.to_str().unwrap().to_string();
+        let mut problem = Problem::from_lines(&lines, &config);
+        fs::remove_file(&path).unwrap();
+
+        assert!(problem.add_customer(Pos { x: 10, y: 10 }, 4, None).is_err());
+        assert_eq!(problem.get_customers().len(), 3);
+    }
+}
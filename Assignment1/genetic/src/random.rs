@@ -0,0 +1,76 @@
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::{Error, Rng, RngCore, SeedableRng};
+
+thread_local! {
+    // Seeded from the OS unless a run sets a seed
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Handle to the random number generator of the current thread, used by
+/// the solvers instead of `rand::thread_rng` so runs can be repeated
+pub struct ThreadRng;
+
+pub fn rng() -> ThreadRng {
+    ThreadRng
+}
+
+impl RngCore for ThreadRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// Seeds the generator of the current thread
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// Draws one seed for each parallel task from the current thread
+pub fn task_seeds(count: usize) -> Vec<u64> {
+    let mut rng = rng();
+    (0..count).map(|_| rng.gen()).collect()
+}
+
+/// Runs a parallel task with its own seed, so the result does not depend
+/// on which thread runs it. The generator of the thread is restored after.
+pub fn with_seed<T, F>(seed: u64, task: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let previous = RNG.with(|rng| rng.replace(StdRng::seed_from_u64(seed)));
+    let result = task();
+    RNG.with(|rng| *rng.borrow_mut() = previous);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        seed(7);
+        let first: Vec<u32> = (0..5).map(|_| rng().gen()).collect();
+        let task = with_seed(3, || rng().gen::<u64>());
+        let rest: Vec<u32> = (0..5).map(|_| rng().gen()).collect();
+
+        seed(7);
+        let again: Vec<u32> = (0..10).map(|_| rng().gen()).collect();
+        assert_eq!([first, rest].concat(), again);
+        assert_eq!(with_seed(3, || rng().gen::<u64>()), task);
+    }
+}
@@ -0,0 +1,288 @@
+use std::fmt::Write;
+use std::fs;
+
+use serde_json::json;
+
+use crate::problem::Problem;
+use crate::solution::Solution;
+
+/// A visit to a customer, or the depot at the start and end of a route.
+/// Vehicles travel one distance unit per time unit and there are no time
+/// windows, so service starts on arrival.
+pub struct Stop {
+    pub node: i32,     // Customer number, 0 for the depot
+    pub distance: f64, // Distance driven when arriving
+    pub arrival: f64,
+    pub service_start: f64,
+    pub departure: f64,
+    pub load: i32, // Load when leaving the stop
+}
+
+pub struct RouteSchedule {
+    pub depot: i32,   // Depot index starting at 1, as in the .res format
+    pub vehicle: i32, // Vehicle index within the depot
+    pub stops: Vec<Stop>,
+    pub capacity: i32,
+    pub max_load: i32,
+    pub max_duration: Option<i32>,
+}
+
+impl RouteSchedule {
+    pub fn get_distance(&self) -> f64 {
+        self.stops.last().map_or(0.0, |stop| stop.distance)
+    }
+
+    pub fn get_duration(&self) -> f64 {
+        self.stops.last().map_or(0.0, |stop| stop.arrival)
+    }
+
+    /// Highest load on the route as a fraction of the capacity
+    pub fn load_utilization(&self) -> f64 {
+        self.max_load as f64 / self.capacity as f64
+    }
+
+    /// Duration as a fraction of the depot's maximum route duration
+    pub fn duration_utilization(&self) -> Option<f64> {
+        self.max_duration
+            .map(|max_duration| self.get_duration() / max_duration as f64)
+    }
+}
+
+pub struct Schedule {
+    pub routes: Vec<RouteSchedule>,
+}
+
+impl Schedule {
+    /// Creates the schedule of every used route. The routes must start and
+    /// end with the vehicle number, as returned by the solvers.
+    pub fn new(solution: &Solution, problem: &Problem) -> Schedule {
+        let model = problem.get_model();
+        let mut routes = Vec::new();
+
+        let mut v_num: i32 = 0;
+        let mut depot: i32 = 0;
+        for vehicle in problem.vehicles.iter() {
+            let depot_index = vehicle.depot - problem.num_customers;
+            if depot == depot_index {
+                v_num += 1;
+            } else {
+                v_num = 1;
+                depot = depot_index;
+            }
+
+            let route = match solution
+                .routes
+                .iter()
+                .find(|route| route[0] == vehicle.number)
+            {
+                Some(route) if route.len() > 2 => route,
+                _ => continue,
+            };
+            let customers = &route[1..(route.len() - 1)];
+
+            let mut distance: f64 = 0.0;
+            let mut time: f64 = 0.0;
+            let mut load = model.initial_load(customers);
+            let mut max_load = load;
+            let mut stops = vec![Stop {
+                node: 0,
+                distance,
+                arrival: time,
+                service_start: time,
+                departure: time,
+                load,
+            }];
+            let mut current_node = vehicle.number as usize;
+            for customer in customers.iter() {
+                let node = *customer as usize;
+                distance += model.get_distance(current_node, node);
+                time += model.get_distance(current_node, node);
+                current_node = node;
+
+                let service_time = problem.get_customer(*customer).get_service_time() as f64;
+                load += model.load_change(node);
+                max_load = max_load.max(load);
+                stops.push(Stop {
+                    node: *customer,
+                    distance,
+                    arrival: time,
+                    service_start: time,
+                    departure: time + service_time,
+                    load,
+                });
+                time += service_time;
+            }
+            // Back to depot
+            distance += model.get_distance(current_node, vehicle.number as usize);
+            time += model.get_distance(current_node, vehicle.number as usize);
+            stops.push(Stop {
+                node: 0,
+                distance,
+                arrival: time,
+                service_start: time,
+                departure: time,
+                load,
+            });
+
+            routes.push(RouteSchedule {
+                depot,
+                vehicle: v_num,
+                stops,
+                capacity: vehicle.capacity,
+                max_load,
+                max_duration: problem.get_depot(vehicle.depot).max_duration,
+            });
+        }
+        Schedule { routes }
+    }
+
+    pub fn to_json(&self) -> String {
+        let routes: Vec<serde_json::Value> = self
+            .routes
+            .iter()
+            .map(|route| {
+                let stops: Vec<serde_json::Value> = route
+                    .stops
+                    .iter()
+                    .map(|stop| {
+                        json!({
+                            "node": stop.node,
+                            "distance": stop.distance,
+                            "arrival": stop.arrival,
+                            "service_start": stop.service_start,
+                            "departure": stop.departure,
+                            "load": stop.load,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "depot": route.depot,
+                    "vehicle": route.vehicle,
+                    "distance": route.get_distance(),
+                    "duration": route.get_duration(),
+                    "capacity": route.capacity,
+                    "max_load": route.max_load,
+                    "load_utilization": route.load_utilization(),
+                    "max_duration": route.max_duration,
+                    "duration_utilization": route.duration_utilization(),
+                    "stops": stops,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&json!({ "routes": routes })).unwrap()
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut output = String::new();
+        for route in self.routes.iter() {
+            write!(
+                &mut output,
+                "Depot {} vehicle {}: distance {:.2}, duration {:.2}, load {}/{} ({:.1}%)",
+                route.depot,
+                route.vehicle,
+                route.get_distance(),
+                route.get_duration(),
+                route.max_load,
+                route.capacity,
+                route.load_utilization() * 100.0
+            )
+            .unwrap();
+            if let (Some(max_duration), Some(utilization)) =
+                (route.max_duration, route.duration_utilization())
+            {
+                write!(
+                    &mut output,
+                    ", duration limit {} ({:.1}%)",
+                    max_duration,
+                    utilization * 100.0
+                )
+                .unwrap();
+            }
+            writeln!(&mut output).unwrap();
+            writeln!(
+                &mut output,
+                "{:>6} {:>10} {:>10} {:>10} {:>10} {:>6}",
+                "Stop", "Distance", "Arrival", "Start", "Departure", "Load"
+            )
+            .unwrap();
+            for stop in route.stops.iter() {
+                let node = if stop.node == 0 {
+                    String::from("Depot")
+                } else {
+                    stop.node.to_string()
+                };
+                writeln!(
+                    &mut output,
+                    "{:>6} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>6}",
+                    node,
+                    stop.distance,
+                    stop.arrival,
+                    stop.service_start,
+                    stop.departure,
+                    stop.load
+                )
+                .unwrap();
+            }
+            writeln!(&mut output).unwrap();
+        }
+        output
+    }
+
+    /// Writes JSON if the path ends with .json, otherwise the table
+    pub fn write_to_path(&self, file_path: &str) {
+        let content = if file_path.ends_with(".json") {
+            self.to_json()
+        } else {
+            self.to_table()
+        };
+        if let Err(err) = fs::write(file_path, content) {
+            panic!("Failed to write to file {}. Error: {}", file_path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    use crate::config::Config;
+    use crate::generator::InstanceGenerator;
+    use crate::parser;
+
+    #[test]
+    fn test_schedule() {
+        let config = Config::default();
+        let mut generator = InstanceGenerator::new(10, 2, 3);
+        generator.service_time = 5;
+        let lines = parser::load_str(&generator.generate());
+        let problem = Problem::from_lines(&lines, &config);
+        let model = problem.get_model();
+
+        // All customers on the first vehicle of each depot
+        let first = problem.vehicles[0].number;
+        let last = problem.vehicles.last().unwrap().number;
+        let solution = Solution::new(vec![
+            vec![first, 1, 2, 3, 4, 5, first],
+            vec![last, 6, 7, 8, 9, 10, last],
+        ]);
+        let schedule = Schedule::new(&solution, &problem);
+        assert_eq!(schedule.routes.len(), 2);
+        assert_eq!(schedule.routes[1].depot, 2);
+
+        for (route, solution_route) in schedule.routes.iter().zip(solution.routes.iter()) {
+            let (load, distance) = solution.evaluate_route(solution_route, model);
+            assert_relative_eq!(route.get_distance(), distance, epsilon = 1e-9);
+            assert_relative_eq!(route.get_duration(), distance + 25.0, epsilon = 1e-9);
+            assert_eq!(route.max_load, load);
+            assert_eq!(route.stops.len(), 7);
+            assert_eq!(route.stops.last().unwrap().load, 0);
+            for stop in route.stops[1..6].iter() {
+                assert_relative_eq!(stop.departure, stop.service_start + 5.0);
+            }
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&schedule.to_json()).unwrap();
+        assert_eq!(json["routes"][0]["stops"][1]["node"], 1);
+    }
+}
@@ -1,46 +1,93 @@
-use std::collections::HashSet;
+use std::cell::RefCell;
 use std::f64;
 use std::fmt;
 use std::i32;
 
 use crate::config::Config;
-use crate::problem::Model;
+use crate::pareto::Objectives;
+use crate::plan::RoutePlan;
+use crate::problem::{CustomerChange, Model};
+use crate::random;
 use crate::solution::Solution;
+use crate::solver::Solver;
 
-use rand::{self, Rng};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use rayon::prelude::*;
 
-#[derive(Clone, Eq, Hash, PartialEq)]
-pub enum Gene {
-    Customer(i32),
-    Depot(i32),
-}
+/// Added to the score for every customer visited after the capacity is exceeded,
+/// unless the fleet size is minimized
+pub const CAPACITY_PENALTY: f64 = 1000.0;
+
+// Set on depot genes, the other bits hold the node number
+const DEPOT_BIT: u32 = 1 << 31;
+
+/// A customer or depot node packed into 32 bits
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+pub struct Gene(u32);
 
 impl Gene {
-    pub fn value(&self) -> i32 {
-        match self {
-            Gene::Customer(val) => i32::clone(val),
-            Gene::Depot(val) => i32::clone(val),
-        }
+    pub fn customer(node: i32) -> Gene {
+        Gene(node as u32)
+    }
+
+    pub fn depot(node: i32) -> Gene {
+        Gene(node as u32 | DEPOT_BIT)
+    }
+
+    pub fn is_depot(self) -> bool {
+        self.0 & DEPOT_BIT != 0
+    }
+
+    pub fn value(self) -> i32 {
+        (self.0 & !DEPOT_BIT) as i32
     }
 }
 
 impl fmt::Display for Gene {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Gene::Customer(val) => write!(f, "{}", val),
-            Gene::Depot(val) => write!(f, "{}", val),
-        }
+        write!(f, "{}", self.value())
     }
 }
 
 impl fmt::Debug for Gene {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Gene::Customer(val) => write!(f, "{}", val),
-            Gene::Depot(val) => write!(f, "{}", val),
-        }
+        write!(f, "{}", self.value())
+    }
+}
+
+/// Bitset over node numbers. Customer and vehicle numbers never overlap,
+/// so the node number alone identifies a gene.
+struct GeneSet {
+    words: Vec<u64>,
+}
+
+impl GeneSet {
+    fn new() -> GeneSet {
+        GeneSet { words: Vec::new() }
+    }
+
+    /// Empties the set and makes room for nodes up to max_node
+    fn reset(&mut self, max_node: usize) {
+        let len = max_node / 64 + 1;
+        self.words.clear();
+        self.words.resize(len, 0);
+    }
+
+    fn insert(&mut self, gene: Gene) {
+        let node = gene.value() as usize;
+        self.words[node / 64] |= 1 << (node % 64);
     }
+
+    fn contains(&self, gene: Gene) -> bool {
+        let node = gene.value() as usize;
+        self.words[node / 64] & (1 << (node % 64)) != 0
+    }
+}
+
+thread_local! {
+    // Reused between crossovers so they do not allocate
+    static CROSSOVER_SETS: RefCell<(GeneSet, GeneSet)> = RefCell::new((GeneSet::new(), GeneSet::new()));
 }
 
 #[derive(Clone)]
@@ -51,79 +98,57 @@ pub struct Chromosome {
 
 impl Chromosome {
     fn get_first_depot_index(&self) -> Option<usize> {
-        let mut index: Option<usize> = None;
-
-        let total_genes = self.genes.len();
-
         // Start index is from the first depot
-        for i in 0..total_genes {
-            match self.genes[i] {
-                Gene::Depot(_) => {
-                    index = Some(i);
-                    break;
-                }
-                _ => (),
-            }
-        }
-        index
+        self.genes.iter().position(|gene| gene.is_depot())
     }
 
+    /// Swaps two customers, keeping the depot genes in place
     pub fn single_swap_mutation(&self) -> Chromosome {
         let mut new_chromosome = self.clone();
-        let chromosome_length = new_chromosome.genes.len();
-        let mut rng = rand::thread_rng();
-        let index_one = rng.gen_range(0, chromosome_length);
-        let index_two = rng.gen_range(0, chromosome_length);
+        let num_customers = self.genes.iter().filter(|gene| !gene.is_depot()).count();
+        if num_customers < 2 {
+            return new_chromosome;
+        }
+        // Pick random genes until two customers are found
+        let mut rng = random::rng();
+        let mut random_customer = || loop {
+            let index = rng.gen_range(0, self.genes.len());
+            if !self.genes[index].is_depot() {
+                return index;
+            }
+        };
+        let index_one = random_customer();
+        let index_two = random_customer();
         new_chromosome.genes.swap(index_one, index_two);
         new_chromosome
     }
 
-    pub fn remove_vehicle_mutation(&self) -> Chromosome {
-        let mut rng = rand::thread_rng();
-        let mut new_chromosome = self.clone();
-
-        let gene_length = new_chromosome.genes.len();
-        let mut index = rng.gen_range(0, gene_length);
-
-        let mut vehicle: Option<usize> = None;
+    /// Decodes the routes, applies the mutation and encodes them again
+    pub fn route_mutation<F>(&self, mutation: F) -> Chromosome
+    where
+        F: FnOnce(&mut RoutePlan),
+    {
+        let mut plan = RoutePlan::from_solution(&self.decode());
+        mutation(&mut plan);
+        plan.to_solution().encode()
+    }
 
-        let mut count = 0;
+    /// Renumbers the genes after a customer is added or removed, and inserts
+    /// an added customer at the cheapest position
+    pub fn update_customers(&self, change: CustomerChange, model: &Model) -> Chromosome {
+        self.route_mutation(|plan| plan.update_customers(change, model))
+    }
 
-        loop {
-            match vehicle {
-                // Find first vehicle after index
-                None => {
-                    let gene = &new_chromosome.genes[index];
-                    match gene {
-                        Gene::Depot(_) => {
-                            vehicle = Some(index);
-                        }
-                        _ => {}
-                    }
-                }
-                // If depot, bubble sort it until it is next to another depot
-                Some(vehicle_index) => {
-                    let next_index = (index + 1) % gene_length;
-                    let next_gene = &new_chromosome.genes[next_index];
-                    match next_gene {
-                        Gene::Depot(_) => {
-                            break;
-                        }
-                        _ => {
-                            new_chromosome.genes.swap(vehicle_index, next_index);
-                            vehicle = Some(next_index);
-                        }
-                    }
-                }
-            }
-            index = (index + 1) % gene_length;
-            count += 1;
-            if count > 10000 {
-                panic!("Stuck in remove vehicle loop");
+    /// Moves the customers of a random vehicle to the vehicle before it
+    pub fn remove_vehicle_mutation(&self) -> Chromosome {
+        let mut rng = random::rng();
+        self.route_mutation(|plan| {
+            let used = plan.used_routes();
+            if let Some(route) = used.choose(&mut rng) {
+                let num_routes = plan.routes.len();
+                plan.merge_routes(*route, (route + num_routes - 1) % num_routes);
             }
-        }
-
-        new_chromosome
+        })
     }
 
     pub fn order_one_crossover(&self, other: &Chromosome) -> (Chromosome, Chromosome) {
@@ -131,37 +156,55 @@ impl Chromosome {
         let mut child_two = other.clone();
 
         let chromosome_length = self.genes.len();
-        let mut rng = rand::thread_rng();
+        let mut rng = random::rng();
         let index_one = rng.gen_range(0, chromosome_length);
         let index_two = rng.gen_range(index_one, chromosome_length);
 
-        // Set of all the genes in the crossover sequence
-        let set_one: HashSet<&Gene> = self.genes[index_one..index_two].iter().collect();
-        let set_two: HashSet<&Gene> = other.genes[index_one..index_two].iter().collect();
+        CROSSOVER_SETS.with(|sets| {
+            let (set_one, set_two) = &mut *sets.borrow_mut();
 
-        let mut insert_index_one = index_two;
-        let mut insert_index_two = index_two;
-
-        for i in 0..chromosome_length {
-            // Wrap index around
-            let new_index = (index_two + i) % chromosome_length;
-            let new_gene_one = &other.genes[new_index];
-            if !set_one.contains(&new_gene_one) {
-                child_one.genes[insert_index_one] = new_gene_one.clone();
-                insert_index_one = (insert_index_one + 1) % chromosome_length;
+            // Set of all the genes in the crossover sequence
+            set_one.reset(chromosome_length);
+            set_two.reset(chromosome_length);
+            for i in index_one..index_two {
+                set_one.insert(self.genes[i]);
+                set_two.insert(other.genes[i]);
             }
 
-            let new_gene_two = &self.genes[new_index];
-            if !set_two.contains(&new_gene_two) {
-                child_two.genes[insert_index_two] = new_gene_two.clone();
-                insert_index_two = (insert_index_two + 1) % chromosome_length;
+            let mut insert_index_one = index_two;
+            let mut insert_index_two = index_two;
+
+            for i in 0..chromosome_length {
+                // Wrap index around
+                let new_index = (index_two + i) % chromosome_length;
+                let new_gene_one = other.genes[new_index];
+                if !set_one.contains(new_gene_one) {
+                    child_one.genes[insert_index_one] = new_gene_one;
+                    insert_index_one = (insert_index_one + 1) % chromosome_length;
+                }
+
+                let new_gene_two = self.genes[new_index];
+                if !set_two.contains(new_gene_two) {
+                    child_two.genes[insert_index_two] = new_gene_two;
+                    insert_index_two = (insert_index_two + 1) % chromosome_length;
+                }
             }
-        }
+        });
 
         (child_one, child_two)
     }
 
+    /// Moves deliveries after their pickups
+    pub fn repair(&self, model: &Model) -> Chromosome {
+        self.route_mutation(|plan| plan.repair_pairs(model))
+    }
+
     pub fn evaluate(&mut self, model: &Model) -> f64 {
+        if model.has_pickups {
+            let score = RoutePlan::from_solution(&self.decode()).cost(model);
+            self.score = Some(score);
+            return score;
+        }
         let total_genes = self.genes.len();
         let start_index = self.get_first_depot_index().unwrap();
 
@@ -175,24 +218,26 @@ impl Chromosome {
 
         loop {
             index = (index + 1) % total_genes;
-            match self.genes[index] {
-                Gene::Depot(node) => {
-                    // Back to last depot
-                    distance = model.get_distance(current_node as usize, vehicle_node as usize);
-                    current_node = node;
-                    vehicle_node = node;
-                    capacity_left = model.get_demand(vehicle_node as usize);
-                }
-                Gene::Customer(node) => {
-                    distance = model.get_distance(current_node as usize, node as usize);
-                    current_node = node;
-                    capacity_left -= model.get_demand(current_node as usize);
+            let gene = self.genes[index];
+            let node = gene.value();
+            if gene.is_depot() {
+                // Back to last depot
+                distance = model.get_distance(current_node as usize, vehicle_node as usize);
+                if current_node != vehicle_node {
+                    score += model.vehicle_cost;
                 }
+                current_node = node;
+                vehicle_node = node;
+                capacity_left = model.get_demand(vehicle_node as usize);
+            } else {
+                distance = model.get_distance(current_node as usize, node as usize);
+                current_node = node;
+                capacity_left -= model.get_demand(current_node as usize);
             }
             score += distance;
 
             if capacity_left < 0 {
-                score += 1000.0;
+                score += model.capacity_penalty;
             }
 
             if index == start_index {
@@ -203,6 +248,65 @@ impl Chromosome {
         self.score = Some(score);
         score
     }
+
+    /// Evaluates total distance, number of vehicles used and the longest route
+    pub fn evaluate_objectives(&self, model: &Model) -> Objectives {
+        let total_genes = self.genes.len();
+        let start_index = self.get_first_depot_index().unwrap();
+
+        let mut objectives = Objectives::new(0.0, 0.0, 0.0);
+        let mut index = start_index;
+        let mut current_node = self.genes[index].value();
+        let mut vehicle_node = current_node;
+        let mut route_distance: f64 = 0.0;
+        let mut route_used = false;
+
+        let mut capacity_left = model.get_demand(vehicle_node as usize);
+
+        loop {
+            index = (index + 1) % total_genes;
+            let gene = self.genes[index];
+            let node = gene.value();
+            if gene.is_depot() {
+                // Back to last depot and close the route
+                route_distance += model.get_distance(current_node as usize, vehicle_node as usize);
+                objectives.distance += route_distance;
+                if route_used {
+                    objectives.vehicles += 1.0;
+                }
+                if route_distance > objectives.longest_route {
+                    objectives.longest_route = route_distance;
+                }
+                current_node = node;
+                vehicle_node = node;
+                route_distance = 0.0;
+                route_used = false;
+                capacity_left = model.get_demand(vehicle_node as usize);
+            } else {
+                route_distance += model.get_distance(current_node as usize, node as usize);
+                current_node = node;
+                route_used = true;
+                capacity_left -= model.get_demand(current_node as usize);
+                if capacity_left < 0 && !model.has_pickups {
+                    objectives.distance += model.capacity_penalty;
+                }
+            }
+
+            if index == start_index {
+                break;
+            }
+        }
+
+        if model.has_pickups {
+            for route in self.decode().routes.iter() {
+                let customers = &route[1..(route.len() - 1)];
+                let violations = model.load_violations(route[0] as usize, customers);
+                objectives.distance += violations as f64 * model.capacity_penalty;
+            }
+        }
+
+        objectives
+    }
 }
 
 impl fmt::Display for Chromosome {
@@ -215,7 +319,7 @@ impl fmt::Display for Chromosome {
     }
 }
 
-trait Decode {
+pub trait Decode {
     fn decode(&self) -> Solution;
 }
 
@@ -257,9 +361,9 @@ impl Population {
 
     fn parent_selection(&self, config: &Config) -> &Chromosome {
         // Selects the best parent out of K random selected parents
-        let mut rng = rand::thread_rng();
+        let mut rng = random::rng();
         let indices: Vec<usize> = (0..config.parent_selection_k)
-            .map(|_| rng.gen_range(0, config.population_size) as usize)
+            .map(|_| rng.gen_range(0, self.chromosomes.len()))
             .collect();
 
         let mut best_parent_score: f64 = f64::MAX;
@@ -283,7 +387,7 @@ impl Population {
         }
     }
 
-    pub fn evolve(&self, config: &Config) -> Population {
+    pub fn evolve(&self, model: &Model, config: &Config) -> Population {
         let mut new_chromosomes: Vec<Chromosome> = Vec::with_capacity(self.chromosomes.len());
 
         for i in 0..config.elite_count {
@@ -293,49 +397,15 @@ impl Population {
 
         let iterations = (self.chromosomes.len() - config.elite_count) / 2;
 
-        new_chromosomes.par_extend((0..iterations).into_par_iter().flat_map(|_| {
-            let mut rng = rand::thread_rng();
-
-            let parent_one: &Chromosome = self.parent_selection(config);
-            let parent_two: &Chromosome = self.parent_selection(config);
-
-            let crossover: f64 = rng.gen();
-            let (child_one, child_two);
-            if crossover < config.crossover_rate {
-                let (a, b) = parent_one.order_one_crossover(parent_two);
-                child_one = a;
-                child_two = b;
-            } else {
-                child_one = parent_one.clone();
-                child_two = parent_two.clone();
-            }
-
-            let mut children = vec![child_one, child_two];
-
-            // Remove vehicle mutation
-            for i in 0..children.len() {
-                let chance: f64 = rng.gen();
-                let times: usize = rng.gen_range(0, config.vehicle_remove_mut_max);
-                if chance < config.vehicle_remove_mut_rate {
-                    for _ in 0..times {
-                        children[i] = children[i].remove_vehicle_mutation();
-                    }
-                }
-            }
-
-            // Single swap mutation
-            for i in 0..children.len() {
-                let chance: f64 = rng.gen();
-                let times: usize = rng.gen_range(0, config.single_swap_mut_max);
-                if chance < config.single_swap_mut_rate {
-                    for _ in 0..times {
-                        children[i] = children[i].single_swap_mutation();
-                    }
-                }
-            }
-
-            children
-        }));
+        new_chromosomes.par_extend(random::task_seeds(iterations).into_par_iter().flat_map(
+            |seed| {
+                random::with_seed(seed, || {
+                    let parent_one: &Chromosome = self.parent_selection(config);
+                    let parent_two: &Chromosome = self.parent_selection(config);
+                    breed(parent_one, parent_two, model, config)
+                })
+            },
+        ));
 
         let mut new_population = Population::new(config);
         new_population.chromosomes = new_chromosomes;
@@ -343,6 +413,96 @@ impl Population {
     }
 }
 
+/// Applies the mutation between 1 and max times with the given probability
+fn mutate<F>(chromosome: &mut Chromosome, rate: f64, max: usize, mutation: F)
+where
+    F: Fn(&Chromosome) -> Chromosome,
+{
+    let mut rng = random::rng();
+    let chance: f64 = rng.gen();
+    if chance < rate && max > 0 {
+        let times: usize = rng.gen_range(1, max + 1);
+        for _ in 0..times {
+            *chromosome = mutation(chromosome);
+        }
+    }
+}
+
+/// Creates two children from the parents using crossover and mutations
+pub fn breed(
+    parent_one: &Chromosome,
+    parent_two: &Chromosome,
+    model: &Model,
+    config: &Config,
+) -> Vec<Chromosome> {
+    let mut rng = random::rng();
+
+    let crossover: f64 = rng.gen();
+    let (child_one, child_two);
+    if crossover < config.crossover_rate {
+        let (a, b) = parent_one.order_one_crossover(parent_two);
+        child_one = a;
+        child_two = b;
+    } else {
+        child_one = parent_one.clone();
+        child_two = parent_two.clone();
+    }
+
+    let mut children = vec![child_one, child_two];
+
+    for child in children.iter_mut() {
+        mutate(
+            child,
+            config.vehicle_remove_mut_rate,
+            config.vehicle_remove_mut_max,
+            |c| c.remove_vehicle_mutation(),
+        );
+        mutate(
+            child,
+            config.single_swap_mut_rate,
+            config.single_swap_mut_max,
+            |c| c.single_swap_mutation(),
+        );
+
+        // Mutations on the decoded routes
+        mutate(
+            child,
+            config.route_reverse_mut_rate,
+            config.route_reverse_mut_max,
+            |c| c.route_mutation(|plan| plan.reverse_segment(&mut random::rng())),
+        );
+        mutate(
+            child,
+            config.route_split_mut_rate,
+            config.route_split_mut_max,
+            |c| c.route_mutation(|plan| plan.split_route(model, &mut random::rng())),
+        );
+        mutate(
+            child,
+            config.route_merge_mut_rate,
+            config.route_merge_mut_max,
+            |c| c.route_mutation(|plan| plan.merge_random_routes(&mut random::rng())),
+        );
+        mutate(
+            child,
+            config.route_transfer_mut_rate,
+            config.route_transfer_mut_max,
+            |c| c.route_mutation(|plan| plan.transfer_route(model, &mut random::rng())),
+        );
+        mutate(
+            child,
+            config.reinsert_mut_rate,
+            config.reinsert_mut_max,
+            |c| c.route_mutation(|plan| plan.reinsert_customer(model, &mut random::rng())),
+        );
+        if model.has_pickups {
+            *child = child.repair(model);
+        }
+    }
+
+    children
+}
+
 impl Decode for Chromosome {
     fn decode(&self) -> Solution {
         let mut routes = Vec::new();
@@ -358,16 +518,13 @@ impl Decode for Chromosome {
             route.push(depot);
             loop {
                 index = (index + 1) % total_genes;
-                match self.genes[index] {
-                    Gene::Depot(val) => {
-                        route.push(depot);
-                        depot = val;
-                        break;
-                    }
-                    Gene::Customer(val) => {
-                        route.push(val);
-                    }
+                let gene = self.genes[index];
+                if gene.is_depot() {
+                    route.push(depot);
+                    depot = gene.value();
+                    break;
                 }
+                route.push(gene.value());
             }
 
             routes.push(route);
@@ -392,38 +549,220 @@ impl Simulation {
             generation: 1,
         }
     }
-    pub fn run(&mut self, model: &Model, config: &Config) {
-        let new_population = self.population.evolve(config);
+    pub fn evaluate(&mut self, model: &Model) {
+        self.population.evaluate(model);
+    }
+}
+
+impl Solver for Simulation {
+    fn initial_size(&self, config: &Config) -> usize {
+        config.population_size
+    }
+
+    fn seed(&mut self, chromosomes: Vec<Chromosome>, model: &Model) {
+        self.population.chromosomes = chromosomes;
+        self.population.evaluate(model);
+    }
+
+    fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        self.population
+            .chromosomes
+            .par_iter_mut()
+            .for_each(|chromosome| *chromosome = chromosome.update_customers(change, model));
+        self.population.evaluate(model);
+    }
+
+    fn run(&mut self, model: &Model, config: &Config) {
+        let new_population = self.population.evolve(model, config);
         self.population = new_population;
         self.population.evaluate(model);
 
         self.generation += 1;
     }
 
-    pub fn get_best_solution(&self) -> Solution {
+    fn get_best_solution(&self) -> Solution {
         let (index, score) = self.population.scores[0];
         let chromosome = &self.population.chromosomes[index];
         let mut solution = chromosome.decode();
         solution.score = Some(score);
         solution
     }
-
-    pub fn evaluate(&mut self, model: &Model) {
-        self.population.evaluate(model);
-    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use crate::generator::InstanceGenerator;
+    use crate::parser;
+    use crate::problem::Problem;
+    use crate::utils::Pos;
+
+    fn generate_problem(num_customers: i32, num_depots: i32, seed: u64) -> Problem {
+        let config = Config::default();
+        let generator = InstanceGenerator::new(num_customers, num_depots, seed);
+        let lines = parser::load_str(&generator.generate());
+        Problem::from_lines(&lines, &config)
+    }
+
+    /// Assigns every customer to a random vehicle in random order
+    fn random_solution(problem: &Problem, seed: u64) -> Solution {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut customers: Vec<i32> = (1..=problem.num_customers).collect();
+        customers.shuffle(&mut rng);
+
+        let mut routes: Vec<Vec<i32>> = problem.vehicles.iter().map(|v| vec![v.number]).collect();
+        for customer in customers {
+            let index = rng.gen_range(0, routes.len());
+            routes[index].push(customer);
+        }
+        for route in routes.iter_mut() {
+            route.push(route[0]);
+        }
+        Solution::new(routes)
+    }
+
+    /// Sorted list of (is depot, value) for every gene
+    fn sorted_genes(chromosome: &Chromosome) -> Vec<(bool, i32)> {
+        let mut genes: Vec<(bool, i32)> = chromosome
+            .genes
+            .iter()
+            .map(|gene| (gene.is_depot(), gene.value()))
+            .collect();
+        genes.sort();
+        genes
+    }
+
+    fn expected_genes(problem: &Problem) -> Vec<(bool, i32)> {
+        let customers = (1..=problem.num_customers).map(|c| (false, c));
+        let depots = problem.vehicles.iter().map(|v| (true, v.number));
+        let mut genes: Vec<(bool, i32)> = customers.chain(depots).collect();
+        genes.sort();
+        genes
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_encode_decode_round_trip(
+            num_customers in 1..60i32,
+            num_depots in 1..5i32,
+            seed in any::<u64>(),
+        ) {
+            let problem = generate_problem(num_customers, num_depots, seed);
+            let solution = random_solution(&problem, seed);
+            let decoded = solution.encode().decode();
+            prop_assert_eq!(decoded.routes, solution.routes);
+        }
+
+        #[test]
+        fn prop_operators_keep_genes(
+            num_customers in 1..60i32,
+            num_depots in 1..5i32,
+            seed in any::<u64>(),
+        ) {
+            let problem = generate_problem(num_customers, num_depots, seed);
+            let expected = expected_genes(&problem);
+            let parent_one = random_solution(&problem, seed).encode();
+            let parent_two = random_solution(&problem, seed.wrapping_add(1)).encode();
+
+            let (child_one, child_two) = parent_one.order_one_crossover(&parent_two);
+            prop_assert_eq!(sorted_genes(&child_one), expected.clone());
+            prop_assert_eq!(sorted_genes(&child_two), expected.clone());
+
+            let mutated = parent_one.single_swap_mutation();
+            prop_assert_eq!(sorted_genes(&mutated), expected.clone());
+
+            let mutated = parent_one.remove_vehicle_mutation();
+            prop_assert_eq!(sorted_genes(&mutated), expected.clone());
+
+            let model = problem.get_model();
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mutated = parent_one.route_mutation(|plan| {
+                plan.reverse_segment(&mut rng);
+                plan.split_route(model, &mut rng);
+                plan.merge_random_routes(&mut rng);
+                plan.transfer_route(model, &mut rng);
+                plan.reinsert_customer(model, &mut rng);
+            });
+            prop_assert_eq!(sorted_genes(&mutated), expected);
+        }
+
+        #[test]
+        fn prop_evaluate_matches_route_costs(
+            num_customers in 1..60i32,
+            num_depots in 1..5i32,
+            seed in any::<u64>(),
+        ) {
+            let problem = generate_problem(num_customers, num_depots, seed);
+            let model = problem.get_model();
+            let solution = random_solution(&problem, seed);
+
+            let mut expected: f64 = 0.0;
+            for route in solution.routes.iter() {
+                let (_, score) = solution.evaluate_route(route, model);
+                expected += score;
+
+                // Penalty for every stop after the capacity is exceeded
+                let mut capacity_left = model.get_demand(route[0] as usize);
+                for customer in route[1..(route.len() - 1)].iter() {
+                    capacity_left -= model.get_demand(*customer as usize);
+                    if capacity_left < 0 {
+                        expected += model.capacity_penalty;
+                    }
+                }
+            }
+
+            let score = solution.encode().evaluate(model);
+            prop_assert!((score - expected).abs() < 1e-6, "{} != {}", score, expected);
+        }
+    }
+
+    #[test]
+    fn test_update_customers() {
+        let mut problem = generate_problem(20, 3, 7);
+        let chromosome = random_solution(&problem, 7).encode();
+
+        let number = problem.add_customer(Pos { x: 10, y: 10 }, 5, None).unwrap();
+        let added = chromosome.update_customers(CustomerChange::Added(number), problem.get_model());
+        assert_eq!(sorted_genes(&added), expected_genes(&problem));
+
+        problem.remove_customer(3).unwrap();
+        let removed = added.update_customers(CustomerChange::Removed(3), problem.get_model());
+        assert_eq!(sorted_genes(&removed), expected_genes(&problem));
+    }
+
+    #[test]
+    fn test_polish() {
+        let problem = generate_problem(30, 2, 11);
+        let mut solution = random_solution(&problem, 11);
+        solution.evaluate(problem.get_model());
+
+        let polished = problem.polish(&solution);
+        assert!(polished.score() <= solution.score());
+        assert_eq!(
+            sorted_genes(&polished.encode()),
+            sorted_genes(&solution.encode())
+        );
+    }
+
     #[test]
     fn test_gene_eq() {
-        let gene_one = Gene::Depot(1);
-        let gene_two = Gene::Depot(1);
-        let gene_three = Gene::Customer(1);
+        let gene_one = Gene::depot(1);
+        let gene_two = Gene::depot(1);
+        let gene_three = Gene::customer(1);
 
         assert_eq!(gene_one, gene_two);
         assert_ne!(gene_one, gene_three);
+        assert!(gene_one.is_depot());
+        assert!(!gene_three.is_depot());
+        assert_eq!(gene_one.value(), 1);
+        assert_eq!(std::mem::size_of::<Gene>(), 4);
     }
 }
@@ -6,6 +6,7 @@ use std::io::Write as wr;
 use std::collections::HashMap;
 
 use crate::config::Config;
+use crate::cvrplib;
 use crate::parser;
 use crate::problem::{Model, Problem};
 use crate::simulation::{Chromosome, Encode, Gene};
@@ -36,7 +37,8 @@ impl Solution {
         }
     }
 
-    fn evaluate_route(&self, route: &Vec<i32>, model: &Model) -> (i32, f64) {
+    /// Returns the load and distance of a single route
+    pub fn evaluate_route(&self, route: &Vec<i32>, model: &Model) -> (i32, f64) {
         let start_node = route[0];
         let mut current_node = start_node;
         let mut cap_used = 0;
@@ -78,17 +80,12 @@ impl Solution {
                 }
             }
 
+            // Unused vehicles are not written
             let route: &Vec<i32> = match route {
-                Some(r) => r,
-                None => {
-                    panic!("No route for vehicle {}", vehicle.number);
-                }
+                Some(r) if r.len() > 2 => r,
+                _ => continue,
             };
 
-            if route.len() == 2 {
-                continue;
-            }
-
             let (cap, score) = self.evaluate_route(route, model);
             write!(&mut output, "{}\t", depot).unwrap();
             write!(&mut output, "{}\t", v_num).unwrap();
@@ -106,10 +103,19 @@ impl Solution {
     }
 
     pub fn write_to_file(&mut self, config: &Config, problem: &Problem, model: &Model) {
-        let content = self.format_output(problem, model);
+        self.write_to_path(&config.solution_path, problem, model);
+    }
+
+    /// Writes the solution in the CVRPLIB format if the path ends with .sol,
+    /// otherwise in the .res format
+    pub fn write_to_path(&mut self, file_path: &str, problem: &Problem, model: &Model) {
+        let content = if file_path.ends_with(".sol") {
+            cvrplib::format_solution(self, model)
+        } else {
+            self.format_output(problem, model)
+        };
         let content = content.trim();
         println!("{}", content);
-        let file_path = config.solution_path.clone();
         let mut file: File = match OpenOptions::new()
             .write(true)
             .create(true)
@@ -118,7 +124,7 @@ impl Solution {
         {
             Ok(file) => file,
             Err(_) => {
-                panic!("Failed to open file {}", config.solution_path);
+                panic!("Failed to open file {}", file_path);
             }
         };
         match file.write_all(content.as_bytes()) {
@@ -139,9 +145,9 @@ impl Encode for Solution {
                 panic!("Error in routes");
             }
             let depot = route[0];
-            genes.push(Gene::Depot(depot));
+            genes.push(Gene::depot(depot));
             for i in 1..num_stops - 1 {
-                genes.push(Gene::Customer(route[i]));
+                genes.push(Gene::customer(route[i]));
             }
         }
         Chromosome { genes, score: None }
@@ -193,11 +199,13 @@ impl OptimalSolution {
         }
     }
 
+    pub fn get_score(&self) -> f64 {
+        self.total_score
+    }
+
     #[allow(dead_code)]
     pub fn get_solution(&self, problem: &Problem) -> Solution {
-        let max_vehicles = problem.max_vehicles;
         let num_customers = problem.num_customers;
-        let num_depots = problem.num_depots;
 
         // Create a map from vehicle to route
         let mut route_map: HashMap<(i32, i32), usize> = HashMap::new();
@@ -209,26 +217,33 @@ impl OptimalSolution {
             route_map.insert((depot, vehicle), i);
         }
 
+        // Vehicles without a route in the file are left unused
         let mut routes = Vec::new();
+        let mut v_num: i32 = 0;
+        let mut depot: i32 = 0;
+        for vehicle in problem.vehicles.iter() {
+            let new_depot = vehicle.depot - num_customers;
+            if depot == new_depot {
+                v_num += 1;
+            } else {
+                v_num = 1;
+                depot = new_depot;
+            }
 
-        let mut vehicle_num = num_customers + 1;
-        for d in 1..=num_depots {
-            for v in 1..=max_vehicles {
-                let mut route = Vec::new();
-                route.push(vehicle_num);
-                let index = (d, v);
-                let route_exists = route_map.get(&index);
-                match route_exists {
-                    Some(route_index) => {
-                        let mut existing_route = self.routes[*route_index].clone();
-                        route.append(&mut existing_route);
-                    }
-                    _ => {}
-                };
-                route.push(vehicle_num);
-                routes.push(route);
-                vehicle_num += 1;
+            let mut route = Vec::new();
+            route.push(vehicle.number);
+            if let Some(route_index) = route_map.remove(&(depot, v_num)) {
+                route.extend(self.routes[route_index].iter());
             }
+            route.push(vehicle.number);
+            routes.push(route);
+        }
+
+        if let Some((depot, vehicle)) = route_map.keys().next() {
+            panic!(
+                "Solution {} uses vehicle {} at depot {}, which is not available",
+                self.path, vehicle, depot
+            );
         }
         Solution::new(routes)
     }
@@ -0,0 +1,184 @@
+use std::f64;
+use std::str::FromStr;
+
+use crate::alns::Alns;
+use crate::annealing::SimulatedAnnealing;
+use crate::config::Config;
+use crate::plan::RoutePlan;
+use crate::problem::{CustomerChange, Model};
+use crate::simulation::{Chromosome, Decode, Simulation};
+use crate::solution::Solution;
+use crate::tabu::TabuSearch;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Genetic,
+    Annealing,
+    Tabu,
+    Alns,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "genetic" | "ga" => Ok(Algorithm::Genetic),
+            "annealing" | "sa" => Ok(Algorithm::Annealing),
+            "tabu" => Ok(Algorithm::Tabu),
+            "alns" => Ok(Algorithm::Alns),
+            _ => Err(format!("Unknown algorithm: {}", s)),
+        }
+    }
+}
+
+/// Common interface for the algorithms solving a problem
+pub trait Solver: Send + Sync {
+    /// Number of initial solutions the solver should be seeded with
+    fn initial_size(&self, config: &Config) -> usize;
+
+    /// Sets the initial solutions
+    fn seed(&mut self, chromosomes: Vec<Chromosome>, model: &Model);
+
+    /// Updates every solution after a customer is added or removed
+    fn update_customers(&mut self, change: CustomerChange, model: &Model);
+
+    /// Runs one generation or iteration batch
+    fn run(&mut self, model: &Model, config: &Config);
+
+    fn get_best_solution(&self) -> Solution;
+}
+
+pub fn create(config: &Config) -> Box<dyn Solver> {
+    match config.algorithm {
+        Algorithm::Genetic => Box::new(Simulation::new(config)),
+        Algorithm::Annealing => Box::new(SimulatedAnnealing::new(config)),
+        Algorithm::Tabu => Box::new(TabuSearch::new()),
+        Algorithm::Alns => Box::new(Alns::new(config)),
+    }
+}
+
+/// Current and best solution of a single solution solver
+pub struct SearchState {
+    pub current: RoutePlan,
+    pub current_cost: f64,
+    pub best: RoutePlan,
+    pub best_cost: f64,
+    pub iteration: usize,
+}
+
+impl SearchState {
+    /// Starts from the best of the chromosomes
+    pub fn new(chromosomes: Vec<Chromosome>, model: &Model) -> SearchState {
+        let mut best: Option<(RoutePlan, f64)> = None;
+        for mut chromosome in chromosomes.into_iter() {
+            let cost = chromosome.evaluate(model);
+            let is_better = match &best {
+                Some((_, best_cost)) => cost < *best_cost,
+                None => true,
+            };
+            if is_better {
+                best = Some((RoutePlan::from_solution(&chromosome.decode()), cost));
+            }
+        }
+        let (plan, cost) = match best {
+            Some(best) => best,
+            None => panic!("No initial solution"),
+        };
+        SearchState {
+            current: plan.clone(),
+            current_cost: cost,
+            best: plan,
+            best_cost: cost,
+            iteration: 0,
+        }
+    }
+
+    /// Replaces the current solution and updates the best solution.
+    /// Returns true if it is a new best solution.
+    pub fn accept(&mut self, plan: RoutePlan, cost: f64, model: &Model) -> bool {
+        self.current = plan;
+        self.current_cost = cost;
+        if cost < self.best_cost {
+            // Recalculate to avoid accumulating rounding errors from deltas
+            self.current_cost = self.current.cost(model);
+            self.best = self.current.clone();
+            self.best_cost = self.current_cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        self.current.update_customers(change, model);
+        self.best.update_customers(change, model);
+        self.current_cost = self.current.cost(model);
+        self.best_cost = self.best.cost(model);
+    }
+
+    pub fn get_best_solution(&self) -> Solution {
+        let mut solution = self.best.to_solution();
+        solution.score = Some(self.best_cost);
+        solution
+    }
+}
+
+pub fn get_state(state: &Option<SearchState>) -> &SearchState {
+    match state {
+        Some(state) => state,
+        None => panic!("Solver not seeded!"),
+    }
+}
+
+pub fn get_state_mut(state: &mut Option<SearchState>) -> &mut SearchState {
+    match state {
+        Some(state) => state,
+        None => panic!("Solver not seeded!"),
+    }
+}
+
+pub fn best_solution(state: &Option<SearchState>) -> Solution {
+    get_state(state).get_best_solution()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::InstanceGenerator;
+    use crate::parser;
+    use crate::problem::Problem;
+
+    #[test]
+    fn test_single_solution_solvers() {
+        let lines = parser::load_str(&InstanceGenerator::new(20, 2, 11).generate());
+        for algorithm in [Algorithm::Annealing, Algorithm::Tabu, Algorithm::Alns].iter() {
+            let mut config = Config::default();
+            config.algorithm = *algorithm;
+            config.solver_iterations = 50;
+            let mut problem = Problem::from_lines(&lines, &config);
+            problem.generate_population(&config);
+
+            let mut best_score = problem.solver.get_best_solution().score();
+            for _ in 0..3 {
+                let solution = problem.simulate(&config);
+                let score = solution.score();
+                assert!(
+                    score <= best_score,
+                    "{:?} made the best score worse",
+                    algorithm
+                );
+                best_score = score;
+
+                // Every customer is visited exactly once
+                let mut customers: Vec<i32> = solution
+                    .routes
+                    .iter()
+                    .flat_map(|route| route[1..route.len() - 1].iter().cloned())
+                    .collect();
+                customers.sort();
+                assert_eq!(customers, (1..=20).collect::<Vec<i32>>(), "{:?}", algorithm);
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::f64;
+
+use rand::Rng;
+
+use crate::config::Config;
+use crate::problem::{CustomerChange, Model};
+use crate::random;
+use crate::simulation::Chromosome;
+use crate::solution::Solution;
+use crate::solver::{self, SearchState, Solver};
+
+/// Tabu search over relocate moves. A customer moved out of a route may
+/// not be moved back into it until the tabu tenure has passed.
+pub struct TabuSearch {
+    state: Option<SearchState>,
+    // (customer, route) -> last iteration the move is tabu
+    tabu_list: HashMap<(i32, usize), usize>,
+}
+
+struct Move {
+    customer: i32,
+    from: usize,
+    to: usize,
+    position: usize,
+    delta: f64,
+}
+
+impl TabuSearch {
+    pub fn new() -> TabuSearch {
+        TabuSearch {
+            state: None,
+            tabu_list: HashMap::new(),
+        }
+    }
+
+    fn is_tabu(&self, customer: i32, route: usize, iteration: usize) -> bool {
+        match self.tabu_list.get(&(customer, route)) {
+            Some(until) => *until >= iteration,
+            None => false,
+        }
+    }
+}
+
+impl Default for TabuSearch {
+    fn default() -> Self {
+        TabuSearch::new()
+    }
+}
+
+impl Solver for TabuSearch {
+    fn initial_size(&self, _config: &Config) -> usize {
+        1
+    }
+
+    fn seed(&mut self, chromosomes: Vec<Chromosome>, model: &Model) {
+        self.state = Some(SearchState::new(chromosomes, model));
+        self.tabu_list.clear();
+    }
+
+    fn update_customers(&mut self, change: CustomerChange, model: &Model) {
+        if let Some(state) = self.state.as_mut() {
+            state.update_customers(change, model);
+        }
+        self.tabu_list.clear();
+    }
+
+    fn run(&mut self, model: &Model, config: &Config) {
+        let mut rng = random::rng();
+
+        for _ in 0..config.solver_iterations {
+            let state = solver::get_state(&self.state);
+            let customers = state.current.customers();
+            if customers.is_empty() {
+                return;
+            }
+            let iteration = state.iteration;
+
+            // Evaluate a sample of the relocate neighbourhood
+            let mut best_move: Option<Move> = None;
+            for _ in 0..config.tabu_candidates {
+                let customer = customers[rng.gen_range(0, customers.len())];
+                let to = rng.gen_range(0, state.current.routes.len());
+
+                let mut plan = state.current.clone();
+                let (from, _) = plan.remove(customer).unwrap();
+                let removal = plan.route_cost(from, model) - state.current.route_cost(from, model);
+                let insertion = plan.route_insertion(to, customer, model);
+                let delta = removal + insertion.cost;
+
+                // Aspiration: tabu moves are allowed if they give a new best solution
+                let tabu = self.is_tabu(customer, to, iteration);
+                let aspiration = state.current_cost + delta < state.best_cost;
+                if tabu && !aspiration {
+                    continue;
+                }
+                let is_better = match &best_move {
+                    Some(best) => delta < best.delta,
+                    None => true,
+                };
+                if is_better {
+                    best_move = Some(Move {
+                        customer,
+                        from,
+                        to,
+                        position: insertion.position,
+                        delta,
+                    });
+                }
+            }
+
+            let state = solver::get_state_mut(&mut self.state);
+            if let Some(best_move) = best_move {
+                let mut plan = state.current.clone();
+                plan.remove(best_move.customer);
+                plan.routes[best_move.to].insert(best_move.position, best_move.customer);
+                let cost = state.current_cost + best_move.delta;
+                state.accept(plan, cost, model);
+                self.tabu_list.insert(
+                    (best_move.customer, best_move.from),
+                    iteration + config.tabu_tenure,
+                );
+            }
+            state.iteration += 1;
+        }
+
+        // Forget moves that are no longer tabu
+        let iteration = solver::get_state(&self.state).iteration;
+        self.tabu_list.retain(|_, until| *until >= iteration);
+    }
+
+    fn get_best_solution(&self) -> Solution {
+        solver::best_solution(&self.state)
+    }
+}
@@ -32,9 +32,34 @@ class MessageType:
 SHOW_SOLUTION = os.getenv('SHOW_SOLUTION', 'false') == 'true'
 SHOW_OPTIMAL_SOLUTION = os.getenv('SHOW_OPTIMAL_SOLUTION', 'false') == 'true'
 GENERATIONS = int(os.getenv('GENERATIONS', '1000'))
+NEW_CUSTOMER_DEMAND = int(os.getenv('NEW_CUSTOMER_DEMAND', '10'))
+
+
+def handle_commands(program, q, commands):
+    # Customers added or removed in the window
+    changed = False
+    while not commands.empty():
+        command, value = commands.get()
+        x, y = value
+        try:
+            if command == 'add':
+                program.add_customer(x, y, NEW_CUSTOMER_DEMAND)
+            elif command == 'remove':
+                # Customers are removed by position, since the numbers
+                # change when an earlier command removes a customer
+                program.remove_customer(x, y)
+        except ValueError as err:
+            print(err)
+            continue
+        changed = True
+    if changed:
+        q.put({
+            MessageType.CUSTOMERS: program.get_customers(),
+            MessageType.DEPOTS: program.get_depots(),
+        })
 
 
-def worker_process(q):
+def worker_process(q, commands):
     program = pygenetic.GeneticProgram()
     q.put({
         MessageType.BOUNDARIES: program.get_boundaries(),
@@ -52,6 +77,7 @@ def worker_process(q):
     program.generate_population()
     old_solution = None
     for _ in range(0, GENERATIONS - 1):
+        handle_commands(program, q, commands)
         solution = program.simulate()
         print(solution)
         if solution != old_solution:
@@ -70,13 +96,14 @@ class WorkerThread(QThread):
     def __init__(self, number=0):
         QThread.__init__(self)
         self.number = number
+        self.commands = mp.Queue()
 
     def __del__(self):
         self.wait()
 
     def run(self):
         queue = mp.Queue()
-        process = mp.Process(target=worker_process, args=[queue])
+        process = mp.Process(target=worker_process, args=[queue, self.commands])
         process.start()
 
         while True:
@@ -138,6 +165,18 @@ class Window(QMainWindow):
         self.depots = self.transform_points(depots)
         self.update()
 
+    def mousePressEvent(self, event):
+        # Left click adds a customer, right click removes the closest one
+        if event.button() == Qt.LeftButton:
+            x, y = self.inverse_transform_point(event.x(), event.y())
+            self.worker.commands.put(('add', (x, y)))
+        elif event.button() == Qt.RightButton and self.customers:
+            def distance(item):
+                x, y = item[1]
+                return (x - event.x()) ** 2 + (y - event.y()) ** 2
+            number, _ = min(self.customers.items(), key=distance)
+            self.worker.commands.put(('remove', self.raw_customers[number]))
+
     def paintEvent(self, event):
         self.painter = QPainter()
         self.painter.begin(self)
@@ -226,6 +265,16 @@ class Window(QMainWindow):
 
         return new_points
 
+    def inverse_transform_point(self, x, y, buffer=50):
+        frame_geometry = self.frameGeometry()
+        width = frame_geometry.width() - 2 * buffer
+        height = frame_geometry.height() - 2 * buffer
+        min_x, min_y, max_x, max_y = self.boundaries
+
+        new_x = (x - buffer) / width * (max_x - min_x) + min_x
+        new_y = max_y - (y - buffer) / height * (max_y - min_y)
+        return (round(new_x), round(new_y))
+
 
 if __name__ == '__main__':
     app = QApplication(sys.argv)
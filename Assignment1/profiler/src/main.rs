@@ -2,7 +2,7 @@ extern crate genetic;
 
 use std::time::Instant;
 
-use genetic::config::CONFIG;
+use genetic::config::Config;
 use genetic::problem::Problem;
 
 fn time_method<F: FnMut()>(name: &str, mut f: F) {
@@ -16,14 +16,18 @@ fn time_method<F: FnMut()>(name: &str, mut f: F) {
 }
 
 fn main() {
-    let problem_path = CONFIG.problem_path.clone();
-    let mut problem = Problem::new(problem_path);
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => panic!("{}", err),
+    };
+    let config = &config;
+    let mut problem = Problem::new(config);
     time_method("generate_population", || {
-        problem.generate_population();
+        problem.generate_population(config);
     });
     time_method("simulate", || {
         for _ in 0..1000 {
-            problem.simulate();
+            problem.simulate(config);
         }
     });
 }
@@ -1,14 +1,11 @@
-#[macro_use]
-extern crate envconfig_derive;
-
 extern crate genetic;
 
-use envconfig::Envconfig;
-
 use std::collections::HashMap;
 
 use genetic::config::Config;
 use genetic::problem::Problem;
+use genetic::utils::Pos;
+use pyo3::exceptions::ValueError;
 use pyo3::prelude::*;
 
 #[pyclass(module = "pygenetic")]
@@ -21,7 +18,10 @@ struct GeneticProgram {
 impl GeneticProgram {
     #[new]
     fn new(obj: &PyRawObject) {
-        let config: Config = Config::init().unwrap();
+        let config: Config = match Config::load() {
+            Ok(config) => config,
+            Err(err) => panic!("{}", err),
+        };
         let mut problem = Problem::new(&config);
         if config.load_solution {
             let optimal_solution_path = config.optimal_solution_path.clone();
@@ -57,6 +57,44 @@ impl GeneticProgram {
         Ok(solution.routes)
     }
 
+    fn simulate_pareto(&mut self) -> PyResult<Vec<Vec<Vec<i32>>>> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let solutions = py.allow_threads(|| self.problem.simulate_pareto(&self.config));
+        Ok(solutions.into_iter().map(|s| s.routes).collect())
+    }
+
+    fn write_pareto_set(&self) {
+        self.problem.write_pareto_set(&self.config);
+    }
+
+    fn polish_solution(&mut self) -> PyResult<Vec<Vec<i32>>> {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let solution = py.allow_threads(|| self.problem.polish_solution(&self.config));
+        Ok(solution.routes)
+    }
+
+    fn write_solution(&self) {
+        self.problem.write_solution(&self.config);
+    }
+
+    fn write_schedule(&self) {
+        self.problem.write_schedule(&self.config);
+    }
+
+    fn add_customer(&mut self, x: i32, y: i32, demand: i32) -> PyResult<i32> {
+        self.problem
+            .add_customer(Pos { x, y }, demand, None)
+            .map_err(ValueError::py_err)
+    }
+
+    fn remove_customer(&mut self, x: i32, y: i32) -> PyResult<i32> {
+        self.problem
+            .remove_customer_at(&Pos { x, y })
+            .map_err(ValueError::py_err)
+    }
+
     fn update_config(&mut self) {
         println!("Crossover: {}", self.config.single_swap_mut_rate);
     }
@@ -0,0 +1,259 @@
+use image::{Rgb, RgbImage};
+
+/// A pixel in one of the colour spaces. Distances are Euclidean in every
+/// space, so in CIELAB they are the CIE 1976 ΔE.
+pub type Color = [f64; 3];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    Rgb,
+    Lab,
+    Hsv, // As a cone: hue is the angle, chroma the radius and value the height
+    Rg,  // Normalized chromaticity, which leaves out the intensity
+}
+
+// D65 white point
+const WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+const DELTA: f64 = 6.0 / 29.0;
+
+fn to_linear(c: f64) -> f64 {
+    let c = c / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f64) -> u8 {
+    let c = if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    to_u8(c * 255.0)
+}
+
+fn to_u8(c: f64) -> u8 {
+    c.round().max(0.0).min(255.0) as u8
+}
+
+fn lab_f(t: f64) -> f64 {
+    if t > DELTA.powi(3) {
+        t.cbrt()
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+fn lab_f_inverse(t: f64) -> f64 {
+    if t > DELTA {
+        t.powi(3)
+    } else {
+        3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+    }
+}
+
+fn rgb_to_lab(pixel: &Rgb<u8>) -> Color {
+    let (r, g, b) = (
+        to_linear(pixel[0] as f64),
+        to_linear(pixel[1] as f64),
+        to_linear(pixel[2] as f64),
+    );
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / WHITE[0];
+    let y = (0.2126729 * r + 0.7151522 * g + 0.0721750 * b) / WHITE[1];
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / WHITE[2];
+    let (fx, fy, fz) = (lab_f(x), lab_f(y), lab_f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn lab_to_rgb(color: &Color) -> Rgb<u8> {
+    let fy = (color[0] + 16.0) / 116.0;
+    let x = lab_f_inverse(fy + color[1] / 500.0) * WHITE[0];
+    let y = lab_f_inverse(fy) * WHITE[1];
+    let z = lab_f_inverse(fy - color[2] / 200.0) * WHITE[2];
+    Rgb([
+        from_linear(3.2404542 * x - 1.5371385 * y - 0.4985314 * z),
+        from_linear(-0.9692660 * x + 1.8760108 * y + 0.0415560 * z),
+        from_linear(0.0556434 * x - 0.2040259 * y + 1.0572252 * z),
+    ])
+}
+
+fn rgb_to_hsv(pixel: &Rgb<u8>) -> Color {
+    let (r, g, b) = (pixel[0] as f64, pixel[1] as f64, pixel[2] as f64);
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let angle = hue * std::f64::consts::PI / 3.0;
+    [chroma * angle.cos(), chroma * angle.sin(), max]
+}
+
+fn hsv_to_rgb(color: &Color) -> Rgb<u8> {
+    let value = color[2];
+    let chroma = color[0].hypot(color[1]).min(value);
+    let hue = color[1]
+        .atan2(color[0])
+        .rem_euclid(2.0 * std::f64::consts::PI)
+        * 3.0
+        / std::f64::consts::PI;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as usize {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    Rgb([to_u8(r + m), to_u8(g + m), to_u8(b + m)])
+}
+
+fn rgb_to_rg(pixel: &Rgb<u8>) -> Color {
+    let sum = pixel[0] as f64 + pixel[1] as f64 + pixel[2] as f64;
+    if sum == 0.0 {
+        // Black has no chromaticity, count it as grey
+        return [255.0 / 3.0, 255.0 / 3.0, 0.0];
+    }
+    // b is 1 - r - g, so it is left out
+    [
+        255.0 * pixel[0] as f64 / sum,
+        255.0 * pixel[1] as f64 / sum,
+        0.0,
+    ]
+}
+
+/// The chromaticity at the highest intensity that fits in RGB
+fn rg_to_rgb(color: &Color) -> Rgb<u8> {
+    let rgb = [color[0], color[1], 255.0 - color[0] - color[1]];
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    if max <= 0.0 {
+        return Rgb([0, 0, 0]);
+    }
+    Rgb([
+        to_u8(rgb[0] * 255.0 / max),
+        to_u8(rgb[1] * 255.0 / max),
+        to_u8(rgb[2] * 255.0 / max),
+    ])
+}
+
+impl ColorSpace {
+    pub fn from_name(name: &str) -> ColorSpace {
+        match name {
+            "rgb" => ColorSpace::Rgb,
+            "lab" => ColorSpace::Lab,
+            "hsv" => ColorSpace::Hsv,
+            "rg" => ColorSpace::Rg,
+            other => panic!("Unknown colour space: {}", other),
+        }
+    }
+
+    pub fn from_rgb(&self, pixel: &Rgb<u8>) -> Color {
+        match self {
+            ColorSpace::Rgb => [pixel[0] as f64, pixel[1] as f64, pixel[2] as f64],
+            ColorSpace::Lab => rgb_to_lab(pixel),
+            ColorSpace::Hsv => rgb_to_hsv(pixel),
+            ColorSpace::Rg => rgb_to_rg(pixel),
+        }
+    }
+
+    pub fn to_rgb(&self, color: &Color) -> Rgb<u8> {
+        match self {
+            ColorSpace::Rgb => Rgb([to_u8(color[0]), to_u8(color[1]), to_u8(color[2])]),
+            ColorSpace::Lab => lab_to_rgb(color),
+            ColorSpace::Hsv => hsv_to_rgb(color),
+            ColorSpace::Rg => rg_to_rgb(color),
+        }
+    }
+
+    /// Centroid of a segment from its mean colour. RGB centroids are
+    /// rounded to whole values, as they always have been.
+    pub fn centroid(&self, mean: &Color) -> Color {
+        match self {
+            ColorSpace::Rgb => [mean[0].round(), mean[1].round(), mean[2].round()],
+            _ => *mean,
+        }
+    }
+}
+
+/// An image converted to a colour space once, so the fitness does not
+/// convert the same pixels again for every individual
+pub struct ColorImage {
+    pub space: ColorSpace,
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ColorImage {
+    pub fn new(image: &RgbImage, space: ColorSpace) -> ColorImage {
+        let (width, height) = image.dimensions();
+        ColorImage {
+            space,
+            width: width as usize,
+            height: height as usize,
+            pixels: image.pixels().map(|pixel| space.from_rgb(pixel)).collect(),
+        }
+    }
+
+    /// Pixel by its index in raster order
+    pub fn get(&self, index: usize) -> &Color {
+        &self.pixels[index]
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> &Color {
+        &self.pixels[y * self.width + x]
+    }
+
+    pub fn pixels(&self) -> std::slice::Iter<Color> {
+        self.pixels.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_round_trip() {
+        let pixels = [
+            Rgb([0, 0, 0]),
+            Rgb([255, 255, 255]),
+            Rgb([200, 30, 60]),
+            Rgb([12, 180, 90]),
+            Rgb([40, 70, 250]),
+        ];
+        for space in [ColorSpace::Rgb, ColorSpace::Lab, ColorSpace::Hsv].iter() {
+            for pixel in pixels.iter() {
+                let back = space.to_rgb(&space.from_rgb(pixel));
+                for c in 0..3 {
+                    assert!((back[c] as i32 - pixel[c] as i32).abs() <= 1);
+                }
+            }
+        }
+
+        // White is L 100 and a, b 0 in CIELAB
+        let white = ColorSpace::Lab.from_rgb(&Rgb([255, 255, 255]));
+        assert!((white[0] - 100.0).abs() < 0.01);
+        assert!(white[1].abs() < 0.01 && white[2].abs() < 0.01);
+
+        // A darker shade has the same chromaticity
+        let rg = ColorSpace::Rg;
+        assert_eq!(
+            rg.from_rgb(&Rgb([250, 50, 100])),
+            rg.from_rgb(&Rgb([125, 25, 50]))
+        );
+        assert_eq!(
+            rg.to_rgb(&rg.from_rgb(&Rgb([125, 25, 50]))),
+            Rgb([255, 51, 102])
+        );
+    }
+}
@@ -1,6 +1,10 @@
+use std::fs;
+
 use envconfig::Envconfig;
 use lazy_static::*;
 
+use crate::color::ColorSpace;
+
 #[derive(Envconfig)]
 pub struct Config {
     #[envconfig(from = "IMAGE_NUMBER", default = "216066")]
@@ -45,11 +49,28 @@ pub struct Config {
     #[envconfig(from = "GENERATIONS", default = "10")]
     pub generations: usize,
 
+    // Number of initial segmentations, with 2 to KMEANS + 1 segments
     #[envconfig(from = "KMEANS", default = "10")]
     pub kmeans: usize,
 
-    #[envconfig(from = "CROSSOVER_SEG_MAX", default = "2")]
-    pub crossover_seg_max: usize,
+    // "mst", "kmeans" or "slic"
+    #[envconfig(from = "INITIALIZATION", default = "mst")]
+    pub initialization: String,
+
+    #[envconfig(from = "SLIC_SUPERPIXELS", default = "400")]
+    pub slic_superpixels: usize,
+
+    // Weight of the x/y distance against the colour distance in SLIC,
+    // higher gives more regular superpixels
+    #[envconfig(from = "SLIC_COMPACTNESS", default = "10.0")]
+    pub slic_compactness: f64,
+
+    #[envconfig(from = "SLIC_ITERATIONS", default = "10")]
+    pub slic_iterations: usize,
+
+    // "rgb", "lab", "hsv" or "rg", used for all colour distances and centroids
+    #[envconfig(from = "COLOR_SPACE", default = "rgb")]
+    color_space: String,
 
     #[envconfig(from = "CROSSOVER_RATE", default = "1.0")]
     pub crossover_rate: f64,
@@ -60,6 +81,26 @@ pub struct Config {
     #[envconfig(from = "MUTATIONS_MAX", default = "20")]
     pub mutations_max: usize,
 
+    // Fraction of mutations that merge two segments instead of changing one link
+    #[envconfig(from = "MERGE_MUTATION_RATE", default = "0.5")]
+    pub merge_mutation_rate: f64,
+
+    // Comma separated, each with an optional ":weight" for the weighted mode.
+    // edge_value, connectivity, overall_deviation, segments, boundary_length,
+    // compactness, texture_gradient or texture_lbp
+    #[envconfig(from = "OBJECTIVES", default = "edge_value,connectivity,overall_deviation")]
+    objectives: String,
+
+    // Hypervolume reference point as comma separated objective values. Empty
+    // uses the worst values of the initial population plus a tenth of their range.
+    #[envconfig(from = "HV_REFERENCE", default = "")]
+    hv_reference: String,
+
+    // File with one comma separated objective vector per line, for IGD and
+    // spread. "{}" is replaced by the image number.
+    #[envconfig(from = "REFERENCE_FRONT", default = "")]
+    reference_front: String,
+
     #[envconfig(from = "WEIGHTED", default = "false")]
     pub weighted: bool,
 
@@ -74,6 +115,10 @@ pub struct Config {
 
     #[envconfig(from = "TOURNAMENT_K", default = "2")]
     pub tournament_k: usize,
+
+    // Pixels a boundary may be off by and still match, as in evaluator/run.py
+    #[envconfig(from = "EVAL_TOLERANCE", default = "4")]
+    pub eval_tolerance: i32,
 }
 
 impl Config {
@@ -81,16 +126,97 @@ impl Config {
         (self.plot_size, self.plot_size * 2 / 3)
     }
 
+    pub fn image_number(&self) -> &str {
+        &self.image_number
+    }
+
+    pub fn color_space(&self) -> ColorSpace {
+        ColorSpace::from_name(&self.color_space)
+    }
+
     pub fn out_path(&self) -> String {
-        format!("{}/{}", self.out_path, self.image_number)
+        self.out_path_for(&self.image_number)
+    }
+
+    pub fn out_path_for(&self, image_number: &str) -> String {
+        format!("{}/{}", self.out_path, image_number)
     }
 
     pub fn image_path(&self) -> String {
-        format!("{}/{}/Test image.jpg", self.image_folder, self.image_number)
+        self.image_path_for(&self.image_number)
+    }
+
+    pub fn image_path_for(&self, image_number: &str) -> String {
+        format!("{}/Test image.jpg", self.image_folder_for(image_number))
+    }
+
+    pub fn image_folder_for(&self, image_number: &str) -> String {
+        format!("{}/{}", self.image_folder, image_number)
+    }
+
+    /// Every folder in the image folder that contains a test image, sorted
+    pub fn all_image_numbers(&self) -> Vec<String> {
+        let entries = match fs::read_dir(&self.image_folder) {
+            Ok(entries) => entries,
+            Err(err) => panic!("Unable to read folder {}. Error: {}", self.image_folder, err),
+        };
+        let mut numbers: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("Test image.jpg").is_file())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        numbers.sort();
+        numbers
+    }
+
+    pub fn hv_reference(&self) -> Option<Vec<f64>> {
+        if self.hv_reference.trim().is_empty() {
+            return None;
+        }
+        let values = self
+            .hv_reference
+            .split(',')
+            .map(|value| match value.trim().parse() {
+                Ok(value) => value,
+                Err(_) => panic!("Invalid value {} in HV_REFERENCE", value),
+            })
+            .collect();
+        Some(values)
+    }
+
+    pub fn reference_front_for(&self, image_number: &str) -> Option<String> {
+        if self.reference_front.is_empty() {
+            None
+        } else {
+            Some(self.reference_front.replace("{}", image_number))
+        }
     }
 
-    pub fn total_weight(&self) -> f64 {
-        self.ev_weight + self.con_weight + self.od_weight
+    /// Objective names with their weights. The weights default to EV_WEIGHT,
+    /// CON_WEIGTH and OD_WEIGHT for the original three objectives and to 0.1
+    pub fn objectives(&self) -> Vec<(String, f64)> {
+        self.objectives
+            .split(',')
+            .map(|spec| spec.trim())
+            .filter(|spec| !spec.is_empty())
+            .map(|spec| {
+                let mut parts = spec.splitn(2, ':');
+                let name = parts.next().unwrap_or("").to_string();
+                let weight = match parts.next() {
+                    Some(weight) => match weight.parse() {
+                        Ok(weight) => weight,
+                        Err(_) => panic!("Invalid weight in objective {}", spec),
+                    },
+                    None => match name.as_str() {
+                        "edge_value" => self.ev_weight,
+                        "connectivity" => self.con_weight,
+                        "overall_deviation" => self.od_weight,
+                        _ => 0.1,
+                    },
+                };
+                (name, weight)
+            })
+            .collect()
     }
 }
 
@@ -0,0 +1,452 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+
+use image::RgbImage;
+
+use crate::config::CONFIG;
+use crate::matrix::{Matrix, Pos};
+use crate::segment::SegmentMatrix;
+use crate::simulation::Fronts;
+
+// Same constants as evaluator/run.py
+const BLACK_THRESHOLD: u8 = 100; // Colors below this are boundary pixels
+const COLOR_SLACK: i32 = 40;
+
+pub type GreyMatrix = Matrix<u8>;
+pub type LabelMatrix = Matrix<usize>;
+
+pub struct GroundTruth {
+    pub name: String,
+    pub border: GreyMatrix,
+    pub labels: LabelMatrix,
+}
+
+#[derive(Clone, Debug)]
+pub struct Score {
+    pub precision: f64,
+    pub recall: f64,
+    pub f_score: f64,
+    pub run_score: f64, // min(precision, recall), the score printed by run.py
+    pub pri: f64,       // Probabilistic Rand Index, higher is better
+    pub voi: f64,       // Variation of Information, lower is better
+}
+
+pub struct ImageScore {
+    pub image_number: String,
+    pub scores: Vec<(String, Score)>, // One score per segmentation in the front
+    best_index: usize,
+}
+
+impl GroundTruth {
+    pub fn new(name: String, border: GreyMatrix) -> GroundTruth {
+        let labels = labels_from_border(&border);
+        GroundTruth {
+            name,
+            border,
+            labels,
+        }
+    }
+}
+
+impl ImageScore {
+    pub fn new(image_number: &str, scores: Vec<(String, Score)>) -> ImageScore {
+        if scores.is_empty() {
+            panic!("No segmentations to score for image {}", image_number);
+        }
+        let mut best_index = 0;
+        for (i, (_, score)) in scores.iter().enumerate() {
+            if score.f_score > scores[best_index].1.f_score {
+                best_index = i;
+            }
+        }
+        ImageScore {
+            image_number: image_number.to_string(),
+            scores,
+            best_index,
+        }
+    }
+
+    /// The segmentation in the front with the highest F score
+    pub fn get_best(&self) -> &(String, Score) {
+        &self.scores[self.best_index]
+    }
+}
+
+pub fn grey_from_image(image: &RgbImage) -> GreyMatrix {
+    // Use the first channel, like fileReader.py
+    let data: Vec<u8> = image.pixels().map(|pixel| pixel[0]).collect();
+    let (width, height) = image.dimensions();
+    GreyMatrix::from_vec(data, width as usize, height as usize)
+}
+
+pub fn load_grey(path: &str) -> GreyMatrix {
+    match image::open(path) {
+        Ok(image) => grey_from_image(&image.into_rgb()),
+        Err(err) => panic!("Unable to load image {}. Error: {}", path, err),
+    }
+}
+
+/// Loads the GT_*.jpg files (or <image>_*.jpg) of an image folder
+pub fn load_ground_truths(image_number: &str) -> Vec<GroundTruth> {
+    let folder = CONFIG.image_folder_for(image_number);
+    let prefix = format!("{}_", image_number);
+    let mut names: Vec<String> = match fs::read_dir(&folder) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| {
+                name.ends_with(".jpg") && (name.starts_with("GT_") || name.starts_with(&prefix))
+            })
+            .collect(),
+        Err(err) => panic!("Unable to read folder {}. Error: {}", folder, err),
+    };
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let border = load_grey(&format!("{}/{}", folder, name));
+            GroundTruth::new(name, border)
+        })
+        .collect()
+}
+
+/// Fraction of the boundary pixels in `from` that have a pixel of similar
+/// color within the tolerance in `to`. This is comparePics from run.py, so
+/// matching a segmentation against a ground truth gives the precision and
+/// the other way around gives the recall.
+pub fn match_fraction(from: &GreyMatrix, to: &GreyMatrix, tolerance: i32) -> f64 {
+    let mut matched = 0;
+    let mut boundary_pixels = 0;
+    for y in 0..from.height {
+        for x in 0..from.width {
+            let pos = Pos::new_usize(y, x);
+            let color = *from.get_pos(&pos) as i32;
+            if color >= BLACK_THRESHOLD as i32 {
+                continue;
+            }
+            boundary_pixels += 1;
+            if color == *to.get_pos(&pos) as i32 {
+                matched += 1;
+                continue;
+            }
+            'search: for dy in -tolerance..=tolerance {
+                for dx in -tolerance..=tolerance {
+                    let other_pos = pos.add(&Pos::new(dy, dx));
+                    if !to.validate_pos(&other_pos) {
+                        continue;
+                    }
+                    let other = *to.get_pos(&other_pos) as i32;
+                    if color - COLOR_SLACK < other && other < color + COLOR_SLACK {
+                        matched += 1;
+                        break 'search;
+                    }
+                }
+            }
+        }
+    }
+    matched as f64 / std::cmp::max(boundary_pixels, 1) as f64
+}
+
+/// Labels the connected regions between boundary pixels, and gives each
+/// boundary pixel the label of the closest region
+pub fn labels_from_border(border: &GreyMatrix) -> LabelMatrix {
+    let unlabeled = usize::MAX;
+    let mut labels = LabelMatrix::new(unlabeled, border.width, border.height);
+    let mut label = 0;
+    for y in 0..border.height {
+        for x in 0..border.width {
+            let pos = Pos::new_usize(y, x);
+            if *labels.get_pos(&pos) != unlabeled || *border.get_pos(&pos) < BLACK_THRESHOLD {
+                continue;
+            }
+            let mut stack: Vec<Pos> = vec![pos];
+            labels.set_at_pos(label, &stack[0]);
+            while let Some(current_pos) = stack.pop() {
+                for new_pos in border.get_sides(&current_pos).into_iter() {
+                    if *labels.get_pos(&new_pos) == unlabeled
+                        && *border.get_pos(&new_pos) >= BLACK_THRESHOLD
+                    {
+                        labels.set_at_pos(label, &new_pos);
+                        stack.push(new_pos);
+                    }
+                }
+            }
+            label += 1;
+        }
+    }
+
+    // Grow the regions into the boundary pixels
+    let mut queue: VecDeque<Pos> = VecDeque::new();
+    for y in 0..border.height {
+        for x in 0..border.width {
+            let pos = Pos::new_usize(y, x);
+            if *labels.get_pos(&pos) != unlabeled {
+                queue.push_back(pos);
+            }
+        }
+    }
+    while let Some(current_pos) = queue.pop_front() {
+        let current_label = *labels.get_pos(&current_pos);
+        for new_pos in border.get_sides(&current_pos).into_iter() {
+            if *labels.get_pos(&new_pos) == unlabeled {
+                labels.set_at_pos(current_label, &new_pos);
+                queue.push_back(new_pos);
+            }
+        }
+    }
+    // An image with only boundary pixels is a single region
+    for index in 0..labels.length {
+        if *labels.get(index) == unlabeled {
+            labels.set(0, index);
+        }
+    }
+    labels
+}
+
+struct Contingency {
+    total: f64,
+    joint: HashMap<(usize, usize), f64>,
+    a: HashMap<usize, f64>,
+    b: HashMap<usize, f64>,
+}
+
+impl Contingency {
+    fn new(a: &LabelMatrix, b: &LabelMatrix) -> Contingency {
+        if a.length != b.length {
+            panic!("Label maps have different sizes");
+        }
+        let mut contingency = Contingency {
+            total: a.length as f64,
+            joint: HashMap::new(),
+            a: HashMap::new(),
+            b: HashMap::new(),
+        };
+        for (label_a, label_b) in a.iter().zip(b.iter()) {
+            *contingency.joint.entry((*label_a, *label_b)).or_insert(0.0) += 1.0;
+            *contingency.a.entry(*label_a).or_insert(0.0) += 1.0;
+            *contingency.b.entry(*label_b).or_insert(0.0) += 1.0;
+        }
+        contingency
+    }
+}
+
+fn pairs(n: f64) -> f64 {
+    n * (n - 1.0) / 2.0
+}
+
+fn entropy(counts: &HashMap<usize, f64>, total: f64) -> f64 {
+    counts
+        .values()
+        .map(|count| {
+            let p = count / total;
+            -p * p.ln()
+        })
+        .sum()
+}
+
+/// Fraction of pixel pairs that are either in the same region in both
+/// label maps or in different regions in both
+pub fn rand_index(a: &LabelMatrix, b: &LabelMatrix) -> f64 {
+    let contingency = Contingency::new(a, b);
+    let total_pairs = pairs(contingency.total);
+    if total_pairs == 0.0 {
+        return 1.0;
+    }
+    let same_both: f64 = contingency.joint.values().map(|n| pairs(*n)).sum();
+    let same_a: f64 = contingency.a.values().map(|n| pairs(*n)).sum();
+    let same_b: f64 = contingency.b.values().map(|n| pairs(*n)).sum();
+    (total_pairs + 2.0 * same_both - same_a - same_b) / total_pairs
+}
+
+/// H(a) + H(b) - 2 I(a, b), in nats
+pub fn variation_of_information(a: &LabelMatrix, b: &LabelMatrix) -> f64 {
+    let contingency = Contingency::new(a, b);
+    let total = contingency.total;
+    let mut mutual_information = 0.0;
+    for ((label_a, label_b), count) in contingency.joint.iter() {
+        let p = count / total;
+        let p_a = contingency.a[label_a] / total;
+        let p_b = contingency.b[label_b] / total;
+        mutual_information += p * (p / (p_a * p_b)).ln();
+    }
+    entropy(&contingency.a, total) + entropy(&contingency.b, total) - 2.0 * mutual_information
+}
+
+/// Scores one segmentation against every ground truth. Precision, recall and
+/// F score come from the ground truth with the best F score, PRI and VoI are
+/// averaged over all ground truths.
+pub fn score_segmentation(
+    border: &GreyMatrix,
+    labels: &LabelMatrix,
+    ground_truths: &[GroundTruth],
+) -> Score {
+    let mut best = Score {
+        precision: 0.0,
+        recall: 0.0,
+        f_score: 0.0,
+        run_score: 0.0,
+        pri: 0.0,
+        voi: 0.0,
+    };
+    let mut pri = 0.0;
+    let mut voi = 0.0;
+    for ground_truth in ground_truths.iter() {
+        let precision = match_fraction(border, &ground_truth.border, CONFIG.eval_tolerance);
+        let recall = match_fraction(&ground_truth.border, border, CONFIG.eval_tolerance);
+        let f_score = if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        };
+        if f_score >= best.f_score {
+            best.precision = precision;
+            best.recall = recall;
+            best.f_score = f_score;
+        }
+        best.run_score = best.run_score.max(precision.min(recall));
+        pri += rand_index(labels, &ground_truth.labels);
+        voi += variation_of_information(labels, &ground_truth.labels);
+    }
+    let num_truths = std::cmp::max(ground_truths.len(), 1) as f64;
+    best.pri = pri / num_truths;
+    best.voi = voi / num_truths;
+    best
+}
+
+pub fn score_segment_matrix(
+    segment_matrix: &SegmentMatrix,
+    ground_truths: &[GroundTruth],
+) -> Score {
+    let border = grey_from_image(&segment_matrix.into_border_image());
+    score_segmentation(&border, &segment_matrix.matrix, ground_truths)
+}
+
+/// Scores every individual in front 0
+pub fn evaluate_front(image_number: &str, fronts: &Fronts) -> Option<ImageScore> {
+    let ground_truths = load_ground_truths(image_number);
+    if ground_truths.is_empty() || fronts.get_best().is_empty() {
+        return None;
+    }
+    let scores = fronts
+        .get_best()
+        .iter()
+        .enumerate()
+        .map(|(i, individual)| {
+            let score = score_segment_matrix(&individual.segment_matrix, &ground_truths);
+            (format!("Individual {}", i), score)
+        })
+        .collect();
+    Some(ImageScore::new(image_number, scores))
+}
+
+/// Scores the border images written to the output folder of an image
+pub fn evaluate_folder(image_number: &str) -> Option<ImageScore> {
+    let ground_truths = load_ground_truths(image_number);
+    let folder = CONFIG.out_path_for(image_number);
+    let mut names: Vec<String> = match fs::read_dir(&folder) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("border-image") && name.ends_with(".jpg"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    if ground_truths.is_empty() || names.is_empty() {
+        return None;
+    }
+    names.sort();
+    let scores = names
+        .into_iter()
+        .map(|name| {
+            let border = load_grey(&format!("{}/{}", folder, name));
+            let labels = labels_from_border(&border);
+            let score = score_segmentation(&border, &labels, &ground_truths);
+            (name, score)
+        })
+        .collect();
+    Some(ImageScore::new(image_number, scores))
+}
+
+pub fn print_scores(image_scores: &[ImageScore]) {
+    println!(
+        "{:>8} {:>22} {:>9} {:>9} {:>9} {:>9} {:>7} {:>7}",
+        "Image", "Best", "Precision", "Recall", "F score", "run.py", "PRI", "VoI"
+    );
+    for image_score in image_scores.iter() {
+        let (name, score) = image_score.get_best();
+        println!(
+            "{:>8} {:>22} {:>8.2}% {:>8.2}% {:>8.2}% {:>8.2}% {:>7.3} {:>7.3}",
+            image_score.image_number,
+            name,
+            score.precision * 100.0,
+            score.recall * 100.0,
+            score.f_score * 100.0,
+            score.run_score * 100.0,
+            score.pri,
+            score.voi
+        );
+    }
+    if !image_scores.is_empty() {
+        let average_f: f64 = image_scores
+            .iter()
+            .map(|image_score| image_score.get_best().1.f_score)
+            .sum::<f64>()
+            / image_scores.len() as f64;
+        let average_run: f64 = image_scores
+            .iter()
+            .map(|image_score| image_score.get_best().1.run_score)
+            .sum::<f64>()
+            / image_scores.len() as f64;
+        println!(
+            "Average F score: {:.2}%, average run.py score: {:.2}%",
+            average_f * 100.0,
+            average_run * 100.0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_halves(width: usize, height: usize, split: usize) -> LabelMatrix {
+        let data = (0..width * height)
+            .map(|i| if i % width < split { 0 } else { 1 })
+            .collect();
+        LabelMatrix::from_vec(data, width, height)
+    }
+
+    #[test]
+    fn test_rand_index_and_voi() {
+        let a = two_halves(4, 2, 2);
+        let b = two_halves(4, 2, 1);
+        assert_eq!(rand_index(&a, &a), 1.0);
+        assert!(variation_of_information(&a, &a).abs() < 1e-12);
+
+        // 28 pairs, 8 together in both and 8 apart in both
+        assert!((rand_index(&a, &b) - 16.0 / 28.0).abs() < 1e-12);
+        // VoI = 2 H(a, b) - H(a) - H(b)
+        let h_b = -(0.25f64 * 0.25f64.ln() + 0.75 * 0.75f64.ln());
+        let h_ab = -(0.25f64 * 0.25f64.ln() * 2.0 + 0.5 * 0.5f64.ln());
+        let expected = 2.0 * h_ab - 2.0f64.ln() - h_b;
+        assert!((variation_of_information(&a, &b) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_match_fraction() {
+        let white = GreyMatrix::new(255, 20, 10);
+        let mut line = white.clone();
+        let mut shifted = white.clone();
+        for y in 0..10 {
+            line.set_at_pos(0, &Pos::new_usize(y, 5));
+            shifted.set_at_pos(0, &Pos::new_usize(y, 8));
+        }
+        assert_eq!(match_fraction(&line, &shifted, 4), 1.0);
+        assert_eq!(match_fraction(&line, &shifted, 2), 0.0);
+        assert_eq!(match_fraction(&line, &white, 4), 0.0);
+
+        let labels = labels_from_border(&line);
+        assert_eq!(*labels.get_pos(&Pos::new_usize(0, 0)), 0);
+        assert_eq!(*labels.get_pos(&Pos::new_usize(0, 19)), 1);
+    }
+}
@@ -0,0 +1,331 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+
+use rand::Rng;
+
+use crate::color::ColorImage;
+use crate::matrix::{Matrix, Pos};
+use crate::segment::SegmentMatrix;
+use crate::utils;
+
+/// Locus-based adjacency genotype: every pixel links to one of its four
+/// neighbours or to itself. The segments are the connected components
+/// of the links.
+#[derive(Clone)]
+pub struct Genotype {
+    pub links: Matrix<usize>,
+}
+
+/// Minimum spanning tree over the colour distance between neighbour pixels
+pub struct SpanningTree {
+    parent: Vec<usize>,    // The root is its own parent
+    order: Vec<usize>,     // Pixels in the order they were added
+    by_weight: Vec<usize>, // Non-root pixels, heaviest edge to the parent first
+    width: usize,
+    height: usize,
+}
+
+struct Edge {
+    weight: f64,
+    from: usize,
+    to: usize,
+}
+
+impl PartialEq for Edge {
+    fn eq(&self, other: &Edge) -> bool {
+        self.weight == other.weight
+    }
+}
+
+impl Eq for Edge {}
+
+impl PartialOrd for Edge {
+    fn partial_cmp(&self, other: &Edge) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Edge {
+    // Reversed, so the binary heap pops the lightest edge
+    fn cmp(&self, other: &Edge) -> Ordering {
+        other.weight.partial_cmp(&self.weight).unwrap()
+    }
+}
+
+fn index_to_pos(index: usize, width: usize) -> Pos {
+    Pos::new_usize(index / width, index % width)
+}
+
+fn pos_to_index(pos: &Pos, width: usize) -> usize {
+    pos.y as usize * width + pos.x as usize
+}
+
+impl SpanningTree {
+    /// Prim's algorithm on the 4-neighbour grid, starting in the top left pixel
+    pub fn new(image: &ColorImage) -> SpanningTree {
+        let (width, height) = (image.width, image.height);
+        let grid = Matrix::new(false, width, height);
+        let length = width * height;
+
+        let mut in_tree = vec![false; length];
+        let mut parent: Vec<usize> = (0..length).collect();
+        let mut weight = vec![0.0; length];
+        let mut order = Vec::with_capacity(length);
+        let mut heap = BinaryHeap::new();
+        heap.push(Edge {
+            weight: 0.0,
+            from: 0,
+            to: 0,
+        });
+        while let Some(edge) = heap.pop() {
+            if in_tree[edge.to] {
+                continue;
+            }
+            in_tree[edge.to] = true;
+            parent[edge.to] = edge.from;
+            weight[edge.to] = edge.weight;
+            order.push(edge.to);
+
+            let pos = index_to_pos(edge.to, width);
+            let pixel = image.get(edge.to);
+            for new_pos in grid.get_sides(&pos).into_iter() {
+                let index = pos_to_index(&new_pos, width);
+                if !in_tree[index] {
+                    heap.push(Edge {
+                        weight: utils::pixel_distance(pixel, image.get(index)),
+                        from: edge.to,
+                        to: index,
+                    });
+                }
+            }
+        }
+
+        let mut by_weight: Vec<usize> = order[1..].to_vec();
+        by_weight.sort_by(|a, b| weight[*b].partial_cmp(&weight[*a]).unwrap());
+        SpanningTree {
+            parent,
+            order,
+            by_weight,
+            width,
+            height,
+        }
+    }
+
+    /// Removes the heaviest edges of the tree until there are `segments`
+    /// segments. Edges that would split off fewer than `min_size` pixels are kept.
+    pub fn genotype(&self, segments: usize, min_size: usize) -> Genotype {
+        let mut links = self.parent.clone();
+
+        // Pixels below each pixel in the tree
+        let mut sizes = vec![1; links.len()];
+        for index in self.order[1..].iter().rev() {
+            sizes[self.parent[*index]] += sizes[*index];
+        }
+
+        let mut removed = 0;
+        for child in self.by_weight.iter() {
+            if removed + 1 >= segments {
+                break;
+            }
+            let size = sizes[*child];
+            if size < min_size {
+                continue;
+            }
+            let mut root = links[*child];
+            while links[root] != root {
+                root = links[root];
+            }
+            if sizes[root] - size < min_size {
+                continue;
+            }
+            // Cut the edge and update the sizes up to the root
+            let mut ancestor = links[*child];
+            loop {
+                sizes[ancestor] -= size;
+                if ancestor == root {
+                    break;
+                }
+                ancestor = links[ancestor];
+            }
+            links[*child] = *child;
+            removed += 1;
+        }
+
+        Genotype {
+            links: Matrix::from_vec(links, self.width, self.height),
+        }
+    }
+}
+
+impl Genotype {
+    /// Links every pixel of a segment to the pixel it was reached from in a
+    /// breadth first search, so the genotype decodes to the same segments
+    pub fn from_segment_matrix(segment_matrix: &SegmentMatrix) -> Genotype {
+        let matrix = &segment_matrix.matrix;
+        let width = matrix.width;
+        let mut links: Vec<usize> = (0..matrix.length).collect();
+        let mut visited = vec![false; matrix.length];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        for start in 0..matrix.length {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            queue.push_back(start);
+            while let Some(index) = queue.pop_front() {
+                let pos = index_to_pos(index, width);
+                for new_pos in matrix.get_sides(&pos).into_iter() {
+                    let new_index = pos_to_index(&new_pos, width);
+                    if !visited[new_index] && matrix.get(new_index) == matrix.get(index) {
+                        visited[new_index] = true;
+                        links[new_index] = index;
+                        queue.push_back(new_index);
+                    }
+                }
+            }
+        }
+        Genotype {
+            links: Matrix::from_vec(links, width, matrix.height),
+        }
+    }
+
+    /// Connected components of the links, numbered in the order of their
+    /// first pixel, like `SegmentMatrix::clean`
+    pub fn decode(&self) -> SegmentMatrix {
+        let length = self.links.length;
+        let mut roots: Vec<usize> = (0..length).collect();
+        fn find(roots: &mut Vec<usize>, mut index: usize) -> usize {
+            while roots[index] != index {
+                roots[index] = roots[roots[index]];
+                index = roots[index];
+            }
+            index
+        }
+        for index in 0..length {
+            let a = find(&mut roots, index);
+            let b = find(&mut roots, *self.links.get(index));
+            if a != b {
+                // Keep the lowest index as root
+                if a < b {
+                    roots[b] = a;
+                } else {
+                    roots[a] = b;
+                }
+            }
+        }
+
+        let mut segment_matrix = SegmentMatrix::new(0, self.links.width, self.links.height);
+        let mut labels = vec![usize::MAX; length];
+        let mut next_label = 0;
+        for index in 0..length {
+            let root = find(&mut roots, index);
+            if labels[root] == usize::MAX {
+                labels[root] = next_label;
+                next_label += 1;
+            }
+            segment_matrix.matrix.set(labels[root], index);
+        }
+        segment_matrix
+    }
+
+    /// Each link comes from either parent with equal probability
+    pub fn uniform_crossover(&self, other: &Genotype) -> Genotype {
+        let mut rng = rand::thread_rng();
+        let links: Vec<usize> = self
+            .links
+            .iter()
+            .zip(other.links.iter())
+            .map(|(a, b)| if rng.gen::<bool>() { *a } else { *b })
+            .collect();
+        Genotype {
+            links: Matrix::from_vec(links, self.links.width, self.links.height),
+        }
+    }
+
+    /// Joins the segments of two side neighbour pixels. The links from `from`
+    /// to its root are reversed first, so `from` becomes the root and linking
+    /// it to `to` does not split its own segment.
+    pub fn join(&mut self, from: usize, to: usize) {
+        let mut path = vec![from];
+        let mut on_path: HashSet<usize> = path.iter().cloned().collect();
+        loop {
+            let next = *self.links.get(path[path.len() - 1]);
+            // The root links to itself, or the links end in a cycle
+            if !on_path.insert(next) {
+                break;
+            }
+            path.push(next);
+        }
+        for pair in path.windows(2) {
+            self.links.set(pair[0], pair[1]);
+        }
+        self.links.set(to, from);
+    }
+
+    /// True if a neighbour of the pixel links to it
+    pub fn is_linked_to(&self, pixel: usize) -> bool {
+        let pos = index_to_pos(pixel, self.links.width);
+        self.links
+            .get_sides(&pos)
+            .iter()
+            .any(|side| *self.links.get(pos_to_index(side, self.links.width)) == pixel)
+    }
+
+    /// Links a random pixel to a random neighbour or to itself. Returns the
+    /// pixel and its old link.
+    pub fn mutate(&mut self) -> (usize, usize) {
+        let mut rng = rand::thread_rng();
+        let index = rng.gen_range(0, self.links.length);
+        let pos = index_to_pos(index, self.links.width);
+        let sides = self.links.get_sides(&pos);
+        let choice = rng.gen_range(0, sides.len() + 1);
+        let link = if choice == sides.len() {
+            index
+        } else {
+            pos_to_index(&sides[choice], self.links.width)
+        };
+        let old = *self.links.get(index);
+        self.links.set(link, index);
+        (index, old)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorSpace;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_mst_genotype() {
+        // Two colour blocks side by side with a bit of noise
+        let mut image = RgbImage::new(20, 10);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let noise = ((x * 7 + y * 3) % 5) as u8;
+            *pixel = if x < 8 {
+                Rgb([200 + noise, 20, 20])
+            } else {
+                Rgb([20, 20 + noise, 200])
+            };
+        }
+        let tree = SpanningTree::new(&ColorImage::new(&image, ColorSpace::Rgb));
+
+        let whole = tree.genotype(1, 1).decode();
+        assert_eq!(whole.get_segments().len(), 1);
+
+        let split = tree.genotype(2, 20).decode();
+        assert_eq!(split.get_segments().len(), 2);
+        assert_ne!(split.matrix.get(0), split.matrix.get(19));
+        for y in 0..10 {
+            assert_eq!(split.matrix.get(y * 20), split.matrix.get(y * 20 + 7));
+            assert_eq!(split.matrix.get(y * 20 + 8), split.matrix.get(y * 20 + 19));
+        }
+
+        // Round trip through a segment matrix keeps the segments
+        let genotype = Genotype::from_segment_matrix(&split);
+        let decoded = genotype.decode();
+        for index in 0..decoded.matrix.length {
+            assert_eq!(decoded.matrix.get(index), split.matrix.get(index));
+        }
+    }
+}
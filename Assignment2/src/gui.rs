@@ -7,6 +7,7 @@ use gdk_pixbuf::{Colorspace, Pixbuf};
 use gtk::prelude::*;
 
 use crate::config::CONFIG;
+use crate::objective;
 use crate::segment::SegmentMatrix;
 use crate::simulation::Fronts;
 
@@ -164,11 +165,14 @@ impl Gui {
     }
 
     fn generate_plots() -> Vec<gtk::Image> {
-        let mut plots: Vec<gtk::Image> = Vec::new();
-        plots.push(plot::generate_image());
-        plots.push(plot::generate_image());
-        plots.push(plot::generate_image());
-        plots
+        // One plot for each pair of neighbouring objectives, only one for two
+        let objectives = CONFIG.objectives().len();
+        let count = if objectives > 2 {
+            objectives
+        } else {
+            objectives.saturating_sub(1)
+        };
+        (0..count).map(|_| plot::generate_image()).collect()
     }
 
     fn generate_labels() -> Vec<gtk::Label> {
@@ -253,51 +257,34 @@ impl Gui {
         gtk_image.set_from_pixbuf(Some(&scaled_pixbuf));
     }
 
+    /// Plots each objective against the next one, and the last against the first
     pub fn update_plots(&self, fronts: &Fronts) {
+        let names = CONFIG.objectives();
         let mut fitness = fronts.get_normalized_fitness();
-
-        for front in fitness.iter_mut() {
-            front.sort_by(|(_, fa), (_, fb)| fa.edge_value.partial_cmp(&fb.edge_value).unwrap())
-        }
-        let ev_con: Vec<Vec<(usize, (f64, f64))>> = fitness
-            .iter()
-            .map(|front| {
-                front
-                    .iter()
-                    .map(|(i, fit)| ((*i), (fit.edge_value, fit.connectivity)))
-                    .collect()
-            })
-            .collect();
-        plot::update_image(&self.plots[0], "Edge value", "Connectivity", ev_con);
-        for front in fitness.iter_mut() {
-            front.sort_by(|(_, fa), (_, fb)| fa.connectivity.partial_cmp(&fb.connectivity).unwrap())
-        }
-        let con_od: Vec<Vec<(usize, (f64, f64))>> = fitness
-            .iter()
-            .map(|front| {
-                front
-                    .iter()
-                    .map(|(i, fit)| ((*i), (fit.connectivity, fit.overall_deviation)))
-                    .collect()
-            })
-            .collect();
-        plot::update_image(&self.plots[1], "Connectivity", "Overall deviation", con_od);
-        for front in fitness.iter_mut() {
-            front.sort_by(|(_, fa), (_, fb)| {
-                fa.overall_deviation
-                    .partial_cmp(&fb.overall_deviation)
-                    .unwrap()
-            })
+        for (x, plot) in self.plots.iter().enumerate() {
+            let y = (x + 1) % names.len();
+            for front in fitness.iter_mut() {
+                front.sort_by(|(_, fa), (_, fb)| {
+                    fa.get_values()[x]
+                        .partial_cmp(&fb.get_values()[x])
+                        .unwrap()
+                })
+            }
+            let points: Vec<Vec<(usize, (f64, f64))>> = fitness
+                .iter()
+                .map(|front| {
+                    front
+                        .iter()
+                        .map(|(i, fit)| ((*i), (fit.get_values()[x], fit.get_values()[y])))
+                        .collect()
+                })
+                .collect();
+            plot::update_image(
+                plot,
+                &objective::display_name(&names[x].0),
+                &objective::display_name(&names[y].0),
+                points,
+            );
         }
-        let od_ev: Vec<Vec<(usize, (f64, f64))>> = fitness
-            .iter()
-            .map(|front| {
-                front
-                    .iter()
-                    .map(|(i, fit)| ((*i), (fit.overall_deviation, fit.edge_value)))
-                    .collect()
-            })
-            .collect();
-        plot::update_image(&self.plots[2], "Overall deviation", "Edge value", od_ev);
     }
 }
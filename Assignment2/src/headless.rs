@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+
+use crate::color::ColorImage;
+use crate::config::CONFIG;
+use crate::evaluate::{self, ImageScore};
+use crate::genotype::SpanningTree;
+use crate::objective;
+use crate::region::RegionGraph;
+use crate::simulation::{ImageFitness, Individual};
+use crate::worker::{ImageSummary, Worker};
+
+/// Image numbers to run on. No arguments means IMAGE_NUMBER,
+/// "all" means every folder in IMAGE_FOLDER.
+pub fn get_image_numbers(args: &[String]) -> Vec<String> {
+    if args.is_empty() {
+        vec![CONFIG.image_number().to_string()]
+    } else if args.len() == 1 && args[0] == "all" {
+        CONFIG.all_image_numbers()
+    } else {
+        args.to_vec()
+    }
+}
+
+/// Runs the MOEA on each image without the GUI, writes the front 0
+/// border images to OUT_FOLDER and prints a summary
+pub fn run(args: &[String]) {
+    let image_numbers = get_image_numbers(args);
+    let mut summaries: Vec<ImageSummary> = Vec::with_capacity(image_numbers.len());
+    let mut image_scores: Vec<ImageScore> = Vec::new();
+    for (i, image_number) in image_numbers.iter().enumerate() {
+        println!("Image {} ({}/{})", image_number, i + 1, image_numbers.len());
+        let start = Instant::now();
+        let mut worker = Worker::new(image_number, Box::new(|_| {}));
+        let fronts = worker.run();
+        let written = worker.save_front(&fronts);
+        worker.save_indicators();
+        let seconds = start.elapsed().as_secs_f64();
+        summaries.push(worker.summarize(&fronts, written, seconds));
+        if let Some(image_score) = evaluate::evaluate_front(image_number, &fronts) {
+            image_scores.push(image_score);
+        }
+    }
+    print_summary(&summaries);
+    if !image_scores.is_empty() {
+        println!("Scores against the ground truth");
+        evaluate::print_scores(&image_scores);
+    }
+}
+
+/// Scores the border images already written to OUT_FOLDER
+pub fn evaluate(args: &[String]) {
+    let image_scores: Vec<ImageScore> = get_image_numbers(args)
+        .iter()
+        .filter_map(|image_number| {
+            let image_score = evaluate::evaluate_folder(image_number);
+            if image_score.is_none() {
+                println!("No border images or ground truth for image {}", image_number);
+            }
+            image_score
+        })
+        .collect();
+    evaluate::print_scores(&image_scores);
+}
+
+fn print_summary(summaries: &[ImageSummary]) {
+    let names: Vec<String> = CONFIG
+        .objectives()
+        .iter()
+        .map(|(name, _)| objective::display_name(name))
+        .collect();
+    println!("Summary");
+    print!(
+        "{:>8} {:>6} {:>8} {:>10}",
+        "Image", "Front", "Written", "Segments"
+    );
+    for name in names.iter() {
+        print!(" {:>12}", name);
+    }
+    println!(" {:>8}", "Time");
+    for summary in summaries.iter() {
+        let min_segments = summary.segments.iter().min().cloned().unwrap_or(0);
+        let max_segments = summary.segments.iter().max().cloned().unwrap_or(0);
+        print!(
+            "{:>8} {:>6} {:>8} {:>10}",
+            summary.image_number,
+            summary.segments.len(),
+            summary.images_written,
+            format!("{}-{}", min_segments, max_segments)
+        );
+        // Best value of each objective in front 0
+        for (i, name) in names.iter().enumerate() {
+            let best = summary
+                .fitness
+                .iter()
+                .map(|f| f.get_values()[i])
+                .fold(f64::MAX, f64::min);
+            print!(" {:>width$.2}", best, width = name.len().max(12));
+        }
+        println!(" {:>7.1}s", summary.seconds);
+    }
+}
+
+const BENCH_ROUNDS: u32 = 5;
+const BENCH_MERGES: usize = 10;
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0 / BENCH_ROUNDS as f64
+}
+
+/// The terms that `ImageFitness` calculates with full scans
+fn get_graph_fitness(graph: &mut RegionGraph, image: &ColorImage) -> [f64; 3] {
+    [
+        graph.get_edge_value(),
+        graph.get_connectivity(),
+        graph.get_overall_deviation(image),
+    ]
+}
+
+/// Times the full fitness scans against building the region graph, and
+/// against building it and updating it after merges like a mutated child
+/// of a crossover does, on the images in IMAGE_FOLDER
+pub fn bench_fitness(args: &[String]) {
+    println!(
+        "{:>8} {:>9} {:>10} {:>10} {:>14} {:>9} {:>9}",
+        "Image", "Size", "Full ms", "Graph ms", "Build+merge ms", "Speedup", "Max diff"
+    );
+    for image_number in get_image_numbers(args).iter() {
+        let image = match image::open(&CONFIG.image_path_for(image_number)) {
+            Ok(image) => image.into_rgb(),
+            Err(_) => panic!("Unable to load image {}!", image_number),
+        };
+        let (width, height) = image.dimensions();
+        let image = ColorImage::new(&image, CONFIG.color_space());
+        let genotype = SpanningTree::new(&image).genotype(CONFIG.kmeans + 1, CONFIG.min_seg_size);
+        let individual = Individual::new(genotype);
+
+        let start = Instant::now();
+        let mut full = [0.0; 3];
+        for _ in 0..BENCH_ROUNDS {
+            full = [
+                individual.calc_edge_value(&image),
+                individual.calc_connectivity(),
+                individual.calc_overall_deviation(&image),
+            ];
+        }
+        let full_time = millis(start.elapsed());
+
+        let start = Instant::now();
+        let mut graph = RegionGraph::new(&individual.segment_matrix, &image);
+        let mut graph_fitness = get_graph_fitness(&mut graph, &image);
+        for _ in 1..BENCH_ROUNDS {
+            graph = RegionGraph::new(&individual.segment_matrix, &image);
+            graph_fitness = get_graph_fitness(&mut graph, &image);
+        }
+        let graph_time = millis(start.elapsed());
+
+        // A crossover child builds its graph before the merges of the merge
+        // mutation, so the build is timed too. Checked against a full scan.
+        let mut merged = graph.clone();
+        let mut child_time = Duration::new(0, 0);
+        for _ in 0..BENCH_ROUNDS {
+            let start = Instant::now();
+            merged = RegionGraph::new(&individual.segment_matrix, &image);
+            for _ in 0..BENCH_MERGES {
+                if merged.len() > 1 {
+                    let region = merged.random_region();
+                    if let Some(neighbour) = merged.get_dominant_neighbour(region) {
+                        merged.merge(region, neighbour);
+                    }
+                }
+            }
+            get_graph_fitness(&mut merged, &image);
+            child_time += start.elapsed();
+        }
+        let merged_fitness = get_graph_fitness(&mut merged, &image);
+        let mut merged_individual = individual.clone();
+        merged_individual.segment_matrix = merged.to_segment_matrix();
+        let merged_full = [
+            merged_individual.calc_edge_value(&image),
+            merged_individual.calc_connectivity(),
+            merged_individual.calc_overall_deviation(&image),
+        ];
+
+        let max_diff = [(&full, &graph_fitness), (&merged_full, &merged_fitness)]
+            .iter()
+            .flat_map(|(a, b)| (0..3).map(move |i| ((a[i] - b[i]) / a[i].abs().max(1.0)).abs()))
+            .fold(0.0, f64::max);
+        println!(
+            "{:>8} {:>9} {:>10.2} {:>10.2} {:>14.2} {:>8.2}x {:>9.1e}",
+            image_number,
+            format!("{}x{}", width, height),
+            full_time,
+            graph_time,
+            millis(child_time),
+            full_time / millis(child_time),
+            max_diff
+        );
+    }
+}
@@ -0,0 +1,332 @@
+use std::fmt;
+use std::fs;
+
+use crate::config::CONFIG;
+use crate::simulation::Fronts;
+
+type Point = Vec<f64>;
+
+/// Indicators of front 0 in one generation
+pub struct Generation {
+    pub generation: usize,
+    pub non_dominated: usize,
+    pub hypervolume: f64,
+    pub igd: Option<f64>,    // Only with a reference front
+    pub spread: Option<f64>, // Only with a reference front
+}
+
+/// Convergence indicators of every generation of one image. The objectives
+/// are scaled so the best values of the initial population are 0 and the
+/// reference point is 1, which keeps the generations comparable.
+pub struct Indicators {
+    ideal: Point,
+    scale: Point,
+    reference_front: Option<Vec<Point>>, // Scaled like the fronts
+    pub generations: Vec<Generation>,
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn min_distance(point: &[f64], points: &[Point]) -> f64 {
+    points
+        .iter()
+        .map(|other| distance(point, other))
+        .fold(f64::MAX, f64::min)
+}
+
+/// Volume dominated by the points and bounded by the reference point, for
+/// minimization. Slices along the last objective and adds the volume of
+/// each slice in one dimension less.
+pub fn hypervolume(points: &[Point], reference: &[f64]) -> f64 {
+    let mut points: Vec<&Point> = points
+        .iter()
+        .filter(|point| point.iter().zip(reference.iter()).all(|(v, r)| v < r))
+        .collect();
+    if points.is_empty() {
+        return 0.0;
+    }
+    let last = reference.len() - 1;
+    if last == 0 {
+        return reference[0] - points.iter().map(|point| point[0]).fold(f64::MAX, f64::min);
+    }
+    points.sort_by(|a, b| a[last].partial_cmp(&b[last]).unwrap());
+    let mut volume = 0.0;
+    for i in 0..points.len() {
+        let upper = if i + 1 < points.len() {
+            points[i + 1][last]
+        } else {
+            reference[last]
+        };
+        let depth = upper - points[i][last];
+        if depth <= 0.0 {
+            continue;
+        }
+        let slice: Vec<Point> = points[..=i]
+            .iter()
+            .map(|point| point[..last].to_vec())
+            .collect();
+        volume += depth * hypervolume(&slice, &reference[..last]);
+    }
+    volume
+}
+
+/// Inverted generational distance: the mean distance from each point of
+/// the reference front to the nearest point of the front
+pub fn igd(front: &[Point], reference_front: &[Point]) -> f64 {
+    reference_front
+        .iter()
+        .map(|point| min_distance(point, front))
+        .sum::<f64>()
+        / reference_front.len() as f64
+}
+
+/// Generalized spread: 0 when the front is evenly spaced and reaches the
+/// extremes of the reference front, which are its best point in each objective
+pub fn spread(front: &[Point], reference_front: &[Point]) -> f64 {
+    let mut unique: Vec<Point> = Vec::new();
+    for point in front.iter() {
+        if !unique.contains(point) {
+            unique.push(point.clone());
+        }
+    }
+    let extremes: f64 = (0..reference_front[0].len())
+        .map(|objective| {
+            let extreme = reference_front
+                .iter()
+                .min_by(|a, b| a[objective].partial_cmp(&b[objective]).unwrap())
+                .expect("Reference front is not empty");
+            min_distance(extreme, &unique)
+        })
+        .sum();
+    let gaps: Vec<f64> = if unique.len() < 2 {
+        vec![0.0; unique.len()]
+    } else {
+        (0..unique.len())
+            .map(|i| {
+                let others: Vec<Point> = unique
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, point)| point.clone())
+                    .collect();
+                min_distance(&unique[i], &others)
+            })
+            .collect()
+    };
+    let mean = gaps.iter().sum::<f64>() / gaps.len() as f64;
+    let numerator = extremes + gaps.iter().map(|gap| (gap - mean).abs()).sum::<f64>();
+    let denominator = extremes + gaps.len() as f64 * mean;
+    if denominator <= 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+fn load_front(path: &str) -> Vec<Point> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => panic!("Unable to read reference front {}. Error: {}", path, err),
+    };
+    let front: Vec<Point> = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.split(',')
+                .map(|value| match value.trim().parse() {
+                    Ok(value) => value,
+                    Err(_) => panic!("Invalid value {} in reference front {}", value, path),
+                })
+                .collect()
+        })
+        .collect();
+    if front.is_empty() {
+        panic!("Reference front {} is empty", path);
+    }
+    front
+}
+
+fn format_option(value: Option<f64>) -> String {
+    value.map_or(String::new(), |value| format!("{:.6}", value))
+}
+
+impl Indicators {
+    /// `reference` is the hypervolume reference point in objective values. Without
+    /// one, the worst values of the initial population plus a tenth of their range are used.
+    pub fn new(
+        initial: &Fronts,
+        reference: Option<Point>,
+        reference_front: Option<Vec<Point>>,
+    ) -> Indicators {
+        let (min, max) = initial.get_ranges();
+        let ideal = min.get_values().to_vec();
+        let reference = match reference {
+            Some(reference) => {
+                if reference.len() != ideal.len() {
+                    panic!("The reference point needs {} values", ideal.len());
+                }
+                reference
+            }
+            None => ideal
+                .iter()
+                .zip(max.get_values().iter())
+                .map(|(min, max)| max + (max - min) / 10.0)
+                .collect(),
+        };
+        let scale = ideal
+            .iter()
+            .zip(reference.iter())
+            .map(|(ideal, reference)| {
+                if reference > ideal {
+                    reference - ideal
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        let mut indicators = Indicators {
+            ideal,
+            scale,
+            reference_front: None,
+            generations: Vec::new(),
+        };
+        indicators.reference_front = reference_front.map(|front| {
+            if front
+                .iter()
+                .any(|point| point.len() != indicators.ideal.len())
+            {
+                panic!(
+                    "The reference front needs {} values per line",
+                    indicators.ideal.len()
+                );
+            }
+            front
+                .iter()
+                .map(|point| indicators.normalize(point))
+                .collect()
+        });
+        indicators
+    }
+
+    pub fn from_config(image_number: &str, initial: &Fronts) -> Indicators {
+        let reference_front = CONFIG.reference_front_for(image_number).and_then(|path| {
+            if fs::metadata(&path).is_ok() {
+                Some(load_front(&path))
+            } else {
+                println!("No reference front {}, skipping IGD and spread", path);
+                None
+            }
+        });
+        Indicators::new(initial, CONFIG.hv_reference(), reference_front)
+    }
+
+    fn normalize(&self, values: &[f64]) -> Point {
+        values
+            .iter()
+            .zip(self.ideal.iter().zip(self.scale.iter()))
+            .map(|(value, (ideal, scale))| (value - ideal) / scale)
+            .collect()
+    }
+
+    /// Calculates the indicators of the next generation
+    pub fn add(&mut self, fronts: &Fronts) -> &Generation {
+        let front: Vec<Point> = fronts
+            .get_best()
+            .iter()
+            .map(|individual| self.normalize(individual.get_fitness().get_values()))
+            .collect();
+        let reference = vec![1.0; self.ideal.len()];
+        let generation = Generation {
+            generation: self.generations.len(),
+            non_dominated: front.len(),
+            hypervolume: hypervolume(&front, &reference),
+            igd: self
+                .reference_front
+                .as_ref()
+                .map(|reference| igd(&front, reference)),
+            spread: self
+                .reference_front
+                .as_ref()
+                .map(|reference| spread(&front, reference)),
+        };
+        self.generations.push(generation);
+        self.generations.last().expect("Generation was added")
+    }
+
+    pub fn save_csv(&self, path: &str) {
+        let mut csv = String::from("generation,non_dominated,hypervolume,igd,spread\n");
+        for generation in self.generations.iter() {
+            csv.push_str(&format!(
+                "{},{},{:.6},{},{}\n",
+                generation.generation,
+                generation.non_dominated,
+                generation.hypervolume,
+                format_option(generation.igd),
+                format_option(generation.spread)
+            ));
+        }
+        if let Err(err) = fs::write(path, csv) {
+            panic!("Unable to write {}. Error: {}", path, err);
+        }
+    }
+}
+
+impl fmt::Display for Generation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Generation {}: {} non-dominated, hypervolume {:.4}",
+            self.generation, self.non_dominated, self.hypervolume
+        )?;
+        if let (Some(igd), Some(spread)) = (self.igd, self.spread) {
+            write!(f, ", IGD {:.4}, spread {:.4}", igd, spread)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hypervolume() {
+        let reference = [1.0, 1.0];
+        let points = vec![vec![0.2, 0.6], vec![0.5, 0.3]];
+        assert!((hypervolume(&points, &reference) - 0.47).abs() < 1e-12);
+        // Dominated points and points outside the reference add nothing
+        let more = vec![
+            vec![0.2, 0.6],
+            vec![0.5, 0.3],
+            vec![0.6, 0.7],
+            vec![1.5, 0.0],
+        ];
+        assert!((hypervolume(&more, &reference) - 0.47).abs() < 1e-12);
+
+        let cube = vec![vec![0.5, 0.5, 0.5], vec![0.0, 0.75, 0.75]];
+        let expected = 0.125 + 0.25 * 0.25 - 0.5 * 0.25 * 0.25;
+        assert!((hypervolume(&cube, &[1.0, 1.0, 1.0]) - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_igd_and_spread() {
+        let reference_front: Vec<Point> = (0..=4)
+            .map(|i| vec![i as f64 / 4.0, 1.0 - i as f64 / 4.0])
+            .collect();
+        assert_eq!(igd(&reference_front, &reference_front), 0.0);
+        assert!(spread(&reference_front, &reference_front).abs() < 1e-12);
+
+        // Only the middle of the front: further away and badly spread
+        let middle = vec![vec![0.5, 0.5], vec![0.5, 0.5]];
+        let expected = (2.0 * 0.25f64.hypot(0.25) + 2.0 * 0.5f64.hypot(0.5)) / 5.0;
+        assert!((igd(&middle, &reference_front) - expected).abs() < 1e-12);
+        assert_eq!(spread(&middle, &reference_front), 1.0);
+    }
+}
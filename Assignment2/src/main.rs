@@ -1,22 +1,59 @@
 #[macro_use]
 extern crate envconfig_derive;
 
+#[cfg(feature = "gui")]
 extern crate cairo;
+#[cfg(feature = "gui")]
 extern crate gio;
+#[cfg(feature = "gui")]
 extern crate gtk;
 extern crate rayon;
 
+#[cfg(feature = "gui")]
 mod app;
+mod color;
 mod config;
+mod evaluate;
+mod genotype;
+#[cfg(feature = "gui")]
 mod gui;
+mod headless;
+mod indicators;
 mod kmeans;
 mod matrix;
+mod objective;
+mod region;
 mod segment;
 mod simulation;
+mod slic;
 mod utils;
+mod worker;
 
+#[cfg(feature = "gui")]
 use app::App;
 
 fn main() {
+    // Usage: assignment2 [--headless|--evaluate|--bench-fitness] [IMAGE_NUMBER ...|all]
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mode = args.first().map(String::as_str);
+    if mode == Some("--headless") {
+        headless::run(&args[1..]);
+    } else if mode == Some("--evaluate") {
+        headless::evaluate(&args[1..]);
+    } else if mode == Some("--bench-fitness") {
+        headless::bench_fitness(&args[1..]);
+    } else {
+        run_gui(&args);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui(_args: &[String]) {
     App::new().build().run();
 }
+
+// Built without GTK, so always run headless
+#[cfg(not(feature = "gui"))]
+fn run_gui(args: &[String]) {
+    headless::run(args);
+}
@@ -64,6 +64,23 @@ where
         }
     }
 
+    pub fn from_vec(data: Vec<T>, width: usize, height: usize) -> Self {
+        let length = width * height;
+        if data.len() != length {
+            panic!("Expected {} values, got {}", length, data.len());
+        }
+        Matrix {
+            data: data,
+            width: width,
+            height: height,
+            length: length,
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<T> {
+        self.data.iter()
+    }
+
     pub fn get(&self, index: usize) -> &T {
         &self.data[index]
     }
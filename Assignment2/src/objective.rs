@@ -0,0 +1,301 @@
+use crate::color::ColorImage;
+use crate::config::CONFIG;
+use crate::matrix::EIGHT_DIRECTIONS;
+use crate::region::RegionGraph;
+use crate::simulation::Fitness;
+use crate::utils;
+
+/// One objective of the MOEA, lower is better. Objectives are calculated
+/// from the region graph, which follows the merge mutations.
+pub trait Objective: Send + Sync {
+    fn evaluate(&self, graph: &mut RegionGraph, image: &ColorImage) -> f64;
+}
+
+pub struct EdgeValue;
+
+pub struct Connectivity;
+
+pub struct OverallDeviation;
+
+pub struct SegmentCount;
+
+/// Side neighbour pixel pairs on the segment boundaries
+pub struct BoundaryLength;
+
+/// Squared perimeter over size summed over the segments, lowest for
+/// round segments. The image border is not part of the perimeter.
+pub struct Compactness;
+
+/// Squared deviation of a texture feature from the segment mean, summed
+/// over the pixels
+pub struct TextureVariance {
+    features: Vec<f64>,
+}
+
+/// The configured objectives, in the order of the fitness values
+pub struct Objectives {
+    names: Vec<String>,
+    weights: Vec<f64>,
+    objectives: Vec<Box<dyn Objective>>,
+}
+
+impl Objective for EdgeValue {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        graph.get_edge_value()
+    }
+}
+
+impl Objective for Connectivity {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        graph.get_connectivity()
+    }
+}
+
+impl Objective for OverallDeviation {
+    fn evaluate(&self, graph: &mut RegionGraph, image: &ColorImage) -> f64 {
+        graph.get_overall_deviation(image)
+    }
+}
+
+impl Objective for SegmentCount {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        graph.len() as f64
+    }
+}
+
+fn get_perimeter(graph: &RegionGraph, label: usize) -> usize {
+    graph.regions[label]
+        .neighbours
+        .values()
+        .map(|border| border.length)
+        .sum()
+}
+
+impl Objective for BoundaryLength {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        let total: usize = (0..graph.regions.len())
+            .filter(|label| graph.is_alive(*label))
+            .map(|label| get_perimeter(graph, label))
+            .sum();
+        // Every boundary is counted from both sides
+        total as f64 / 2.0
+    }
+}
+
+impl Objective for Compactness {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        (0..graph.regions.len())
+            .filter(|label| graph.is_alive(*label))
+            .map(|label| {
+                let perimeter = get_perimeter(graph, label) as f64;
+                perimeter * perimeter / graph.regions[label].size as f64
+            })
+            .sum()
+    }
+}
+
+/// Pixel next to the index, clamped to the image
+fn get_neighbour(image: &ColorImage, index: usize, dy: i32, dx: i32) -> usize {
+    let x = (index % image.width) as i32 + dx;
+    let y = (index / image.width) as i32 + dy;
+    let x = x.max(0).min(image.width as i32 - 1) as usize;
+    let y = y.max(0).min(image.height as i32 - 1) as usize;
+    y * image.width + x
+}
+
+impl TextureVariance {
+    /// Colour gradient magnitude from the central differences
+    pub fn gradient(image: &ColorImage) -> TextureVariance {
+        let features = (0..image.width * image.height)
+            .map(|index| {
+                let dx = utils::pixel_distance(
+                    image.get(get_neighbour(image, index, 0, 1)),
+                    image.get(get_neighbour(image, index, 0, -1)),
+                );
+                let dy = utils::pixel_distance(
+                    image.get(get_neighbour(image, index, 1, 0)),
+                    image.get(get_neighbour(image, index, -1, 0)),
+                );
+                dx.hypot(dy)
+            })
+            .collect();
+        TextureVariance { features }
+    }
+
+    /// Rotation invariant uniform local binary patterns of the grey level:
+    /// the number of neighbours at least as bright for patterns with at
+    /// most two changes around the circle, 9 for the rest
+    pub fn lbp(image: &ColorImage) -> TextureVariance {
+        let grey: Vec<f64> = image
+            .pixels()
+            .map(|color| {
+                let rgb = image.space.to_rgb(color);
+                0.299 * rgb[0] as f64 + 0.587 * rgb[1] as f64 + 0.114 * rgb[2] as f64
+            })
+            .collect();
+        let features = (0..grey.len())
+            .map(|index| {
+                // The directions go around the circle
+                let bits: Vec<bool> = EIGHT_DIRECTIONS
+                    .iter()
+                    .map(|pos| grey[get_neighbour(image, index, pos.y, pos.x)] >= grey[index])
+                    .collect();
+                let changes = (0..8).filter(|i| bits[*i] != bits[(i + 1) % 8]).count();
+                if changes <= 2 {
+                    bits.iter().filter(|bit| **bit).count() as f64
+                } else {
+                    9.0
+                }
+            })
+            .collect();
+        TextureVariance { features }
+    }
+}
+
+impl Objective for TextureVariance {
+    fn evaluate(&self, graph: &mut RegionGraph, _image: &ColorImage) -> f64 {
+        (0..graph.regions.len())
+            .filter(|label| graph.is_alive(*label))
+            .map(|label| {
+                let region = &graph.regions[label];
+                let (sum, squares) =
+                    region
+                        .pixels
+                        .iter()
+                        .fold((0.0, 0.0), |(sum, squares), index| {
+                            let feature = self.features[*index];
+                            (sum + feature, squares + feature * feature)
+                        });
+                squares - sum * sum / region.size as f64
+            })
+            .sum()
+    }
+}
+
+impl Objectives {
+    pub fn from_config(image: &ColorImage) -> Objectives {
+        Objectives::new(&CONFIG.objectives(), image)
+    }
+
+    /// Objectives by name, each with its weight for the weighted mode
+    pub fn new(specs: &[(String, f64)], image: &ColorImage) -> Objectives {
+        let objectives = specs
+            .iter()
+            .map(|(name, _)| -> Box<dyn Objective> {
+                match name.as_str() {
+                    "edge_value" => Box::new(EdgeValue),
+                    "connectivity" => Box::new(Connectivity),
+                    "overall_deviation" => Box::new(OverallDeviation),
+                    "segments" => Box::new(SegmentCount),
+                    "boundary_length" => Box::new(BoundaryLength),
+                    "compactness" => Box::new(Compactness),
+                    "texture_gradient" => Box::new(TextureVariance::gradient(image)),
+                    "texture_lbp" => Box::new(TextureVariance::lbp(image)),
+                    other => panic!("Unknown objective: {}", other),
+                }
+            })
+            .collect();
+        Objectives {
+            names: specs.iter().map(|(name, _)| name.clone()).collect(),
+            weights: specs.iter().map(|(_, weight)| *weight).collect(),
+            objectives,
+        }
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn evaluate(&self, graph: &mut RegionGraph, image: &ColorImage) -> Fitness {
+        Fitness::new(
+            self.objectives
+                .iter()
+                .map(|objective| objective.evaluate(graph, image))
+                .collect(),
+        )
+    }
+
+    pub fn get_weighted(&self, fitness: &Fitness) -> f64 {
+        let total: f64 = self.weights.iter().sum();
+        fitness
+            .get_values()
+            .iter()
+            .zip(self.weights.iter())
+            .map(|(value, weight)| value * weight)
+            .sum::<f64>()
+            / total
+    }
+}
+
+/// "overall_deviation" as "Overall deviation", for labels
+pub fn display_name(name: &str) -> String {
+    let name = name.replace('_', " ");
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorSpace;
+    use crate::segment::SegmentMatrix;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_objectives() {
+        // A 6x4 square in the top left of a 10x4 image, the rest is striped
+        let mut image = RgbImage::new(10, 4);
+        let mut segment_matrix = SegmentMatrix::new(0, 10, 4);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            *pixel = if x < 6 {
+                Rgb([100, 100, 100])
+            } else if y % 2 == 0 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            };
+            segment_matrix
+                .matrix
+                .set(if x < 6 { 0 } else { 1 }, (y * 10 + x) as usize);
+        }
+        let image = ColorImage::new(&image, ColorSpace::Rgb);
+        let mut graph = RegionGraph::new(&segment_matrix, &image);
+
+        let specs: Vec<(String, f64)> = [
+            "connectivity",
+            "segments",
+            "boundary_length",
+            "compactness",
+            "texture_gradient",
+            "texture_lbp",
+        ]
+        .iter()
+        .map(|name| (name.to_string(), 1.0))
+        .collect();
+        let objectives = Objectives::new(&specs, &image);
+        let values = objectives
+            .evaluate(&mut graph, &image)
+            .get_values()
+            .to_vec();
+        assert_eq!(values[0], 2.0 * 10.0 / 8.0);
+        assert_eq!(values[1], 2.0);
+        assert_eq!(values[2], 4.0);
+        assert_eq!(values[3], 16.0 / 24.0 + 16.0 / 16.0);
+        // The flat square has no texture, the stripes do
+        assert!(values[4] > 0.0 && values[5] > 0.0);
+        let square: Vec<usize> = (0..40).filter(|index| index % 10 < 5).collect();
+        for features in [
+            TextureVariance::gradient(&image).features,
+            TextureVariance::lbp(&image).features,
+        ]
+        .iter()
+        {
+            assert!(square.iter().all(|index| features[*index] == features[0]));
+        }
+
+        assert_eq!(display_name("overall_deviation"), "Overall deviation");
+    }
+}
@@ -0,0 +1,617 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap};
+
+use rand::Rng;
+
+use crate::color::{Color, ColorImage};
+use crate::matrix::Matrix;
+use crate::segment::SegmentMatrix;
+use crate::utils;
+
+// (dy, dx) of the neighbours after a pixel in raster order, so every
+// pair of 8-neighbours is visited once. E and S are sides.
+const FORWARD: [(i32, i32); 4] = [(0, 1), (1, 1), (1, 0), (1, -1)];
+
+/// Boundary between two regions
+#[derive(Clone, Debug)]
+pub struct Border {
+    pub length: usize,                  // Side neighbour pixel pairs
+    pub pixels: Option<(usize, usize)>, // One side pair, (pixel in this region, pixel in the neighbour)
+    pub pairs: usize,                   // 8-neighbour pixel pairs
+    pub distance: f64,                  // Colour distance summed over the 8-neighbour pairs
+}
+
+#[derive(Clone, Default)]
+pub struct Region {
+    pub size: usize,
+    pub color_sum: Color,
+    pub pixels: Vec<usize>,
+    pub neighbours: HashMap<usize, Border>,
+    deviation: f64, // Distance from the centroid summed over the pixels
+    dirty: bool,    // The deviation must be recalculated
+}
+
+/// Two neighbour regions ordered by the distance between their mean
+/// colours, reversed so the binary heap pops the closest pair
+struct Pair {
+    distance: f64,
+    a: usize,
+    b: usize,
+}
+
+impl PartialEq for Pair {
+    fn eq(&self, other: &Pair) -> bool {
+        self.distance == other.distance
+    }
+}
+
+impl Eq for Pair {}
+
+impl PartialOrd for Pair {
+    fn partial_cmp(&self, other: &Pair) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pair {
+    fn cmp(&self, other: &Pair) -> Ordering {
+        other.distance.partial_cmp(&self.distance).unwrap()
+    }
+}
+
+/// Region adjacency graph of a segment matrix. Merged regions are joined
+/// in a union-find over the original labels, so a merge only touches the
+/// neighbour lists of the two regions and the label matrix is resolved
+/// when it is needed.
+///
+/// The graph also keeps the terms of the fitness: the boundary pairs and
+/// their colour distances for connectivity and edge value, and the
+/// deviation of each region. A merge or a pixel reassignment only updates
+/// the terms of the regions involved.
+#[derive(Clone)]
+pub struct RegionGraph {
+    pub regions: Vec<Region>,
+    parents: Vec<usize>,
+    labels: Matrix<usize>,
+    positions: Vec<usize>, // Index of each pixel in the pixel list of its region
+    alive: usize,
+    boundary_pairs: usize,
+    boundary_distance: f64,
+}
+
+impl Border {
+    fn new() -> Border {
+        Border {
+            length: 0,
+            pixels: None,
+            pairs: 0,
+            distance: 0.0,
+        }
+    }
+
+    fn join(&mut self, other: Border) {
+        self.length += other.length;
+        self.pairs += other.pairs;
+        self.distance += other.distance;
+        if self.pixels.is_none() {
+            self.pixels = other.pixels;
+        }
+    }
+}
+
+impl Region {
+    pub fn get_color_mean(&self) -> Color {
+        let size = self.size as f64;
+        [
+            self.color_sum[0] / size,
+            self.color_sum[1] / size,
+            self.color_sum[2] / size,
+        ]
+    }
+
+    fn update_deviation(&mut self, image: &ColorImage) {
+        // Same centroid as `Segment::get_pixel_centroid`
+        let centroid = image.space.centroid(&self.get_color_mean());
+        self.deviation = self
+            .pixels
+            .iter()
+            .map(|index| utils::pixel_distance(&centroid, image.get(*index)))
+            .sum();
+        self.dirty = false;
+    }
+}
+
+impl RegionGraph {
+    /// The segment matrix must be cleaned, so the segments are numbered 0..N
+    pub fn new(segment_matrix: &SegmentMatrix, image: &ColorImage) -> RegionGraph {
+        let matrix = &segment_matrix.matrix;
+        let num_regions = matrix.iter().max().map_or(0, |max| max + 1);
+        let mut graph = RegionGraph {
+            regions: vec![Region::default(); num_regions],
+            parents: (0..num_regions).collect(),
+            labels: matrix.clone(),
+            positions: vec![0; matrix.length],
+            alive: num_regions,
+            boundary_pairs: 0,
+            boundary_distance: 0.0,
+        };
+
+        for y in 0..matrix.height {
+            for x in 0..matrix.width {
+                let index = y * matrix.width + x;
+                let label = *matrix.get(index);
+                let pixel = image.get(index);
+                let region = &mut graph.regions[label];
+                region.size += 1;
+                graph.positions[index] = region.pixels.len();
+                region.pixels.push(index);
+                for c in 0..3 {
+                    region.color_sum[c] += pixel[c];
+                }
+                for (dy, dx) in FORWARD.iter() {
+                    let (ny, nx) = (y as i32 + dy, x as i32 + dx);
+                    if ny >= matrix.height as i32 || nx < 0 || nx >= matrix.width as i32 {
+                        continue;
+                    }
+                    let other = ny as usize * matrix.width + nx as usize;
+                    let other_label = *matrix.get(other);
+                    if other_label != label {
+                        let distance = utils::pixel_distance(pixel, image.get(other));
+                        let side = *dy == 0 || *dx == 0;
+                        graph.add_pair(label, other_label, index, other, distance, side);
+                    }
+                }
+            }
+        }
+        for region in graph.regions.iter_mut() {
+            region.update_deviation(image);
+        }
+        graph
+    }
+
+    fn add_pair(&mut self, a: usize, b: usize, pixel_a: usize, pixel_b: usize, distance: f64, side: bool) {
+        for (from, to, pixels) in [(a, b, (pixel_a, pixel_b)), (b, a, (pixel_b, pixel_a))].iter() {
+            let border = self.regions[*from]
+                .neighbours
+                .entry(*to)
+                .or_insert_with(Border::new);
+            border.pairs += 1;
+            border.distance += distance;
+            if side {
+                border.length += 1;
+                if border.pixels.is_none() {
+                    border.pixels = Some(*pixels);
+                }
+            }
+        }
+        self.boundary_pairs += 1;
+        self.boundary_distance += distance;
+    }
+
+    fn remove_pair(&mut self, a: usize, b: usize, pixel: usize, distance: f64, side: bool) {
+        for (from, to) in [(a, b), (b, a)].iter() {
+            let neighbours = &mut self.regions[*from].neighbours;
+            let border = neighbours.get_mut(to).expect("Neighbour lists out of sync");
+            border.pairs -= 1;
+            border.distance -= distance;
+            if side {
+                border.length -= 1;
+            }
+            if let Some((p, q)) = border.pixels {
+                if p == pixel || q == pixel {
+                    border.pixels = None;
+                }
+            }
+            if border.pairs == 0 {
+                neighbours.remove(to);
+            }
+        }
+        self.boundary_pairs -= 1;
+        self.boundary_distance -= distance;
+    }
+
+    /// Current label of a region that may have been merged
+    pub fn find(&mut self, label: usize) -> usize {
+        let mut label = label;
+        while self.parents[label] != label {
+            self.parents[label] = self.parents[self.parents[label]];
+            label = self.parents[label];
+        }
+        label
+    }
+
+    pub fn is_alive(&self, label: usize) -> bool {
+        self.parents[label] == label && self.regions[label].size > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive
+    }
+
+    pub fn random_region(&self) -> usize {
+        let mut rng = rand::thread_rng();
+        loop {
+            let label = rng.gen_range(0, self.regions.len());
+            if self.is_alive(label) {
+                return label;
+            }
+        }
+    }
+
+    /// The neighbour sharing the longest border with the region
+    pub fn get_dominant_neighbour(&self, label: usize) -> Option<usize> {
+        self.regions[label]
+            .neighbours
+            .iter()
+            .filter(|(_, border)| border.length > 0)
+            .max_by(|(a, border_a), (b, border_b)| {
+                border_a.length.cmp(&border_b.length).then(b.cmp(a))
+            })
+            .map(|(neighbour, _)| *neighbour)
+    }
+
+    /// Merges two neighbour regions and returns the label of the merged region.
+    /// The region with fewer neighbours is moved into the other.
+    pub fn merge(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            panic!("Cannot merge region {} with itself", a);
+        }
+        let (keep, gone) =
+            if self.regions[a].neighbours.len() >= self.regions[b].neighbours.len() {
+                (a, b)
+            } else {
+                (b, a)
+            };
+
+        let mut gone_region = std::mem::take(&mut self.regions[gone]);
+        // The boundary between the two is now inside the merged region
+        if let Some(border) = self.regions[keep].neighbours.remove(&gone) {
+            self.boundary_pairs -= border.pairs;
+            self.boundary_distance -= border.distance;
+        }
+        for (neighbour, border) in gone_region.neighbours.drain() {
+            if neighbour == keep {
+                continue;
+            }
+            let back = self.regions[neighbour]
+                .neighbours
+                .remove(&gone)
+                .expect("Neighbour lists out of sync");
+            self.regions[neighbour]
+                .neighbours
+                .entry(keep)
+                .or_insert_with(Border::new)
+                .join(back);
+            self.regions[keep]
+                .neighbours
+                .entry(neighbour)
+                .or_insert_with(Border::new)
+                .join(border);
+        }
+
+        let region = &mut self.regions[keep];
+        region.size += gone_region.size;
+        for c in 0..3 {
+            region.color_sum[c] += gone_region.color_sum[c];
+        }
+        if gone_region.pixels.len() > region.pixels.len() {
+            std::mem::swap(&mut gone_region.pixels, &mut region.pixels);
+        }
+        let start = region.pixels.len();
+        region.pixels.extend(gone_region.pixels);
+        for (position, pixel) in region.pixels.iter().enumerate().skip(start) {
+            self.positions[*pixel] = position;
+        }
+        region.dirty = true;
+        self.parents[gone] = keep;
+        self.alive -= 1;
+        keep
+    }
+
+    /// Current label of the region the pixel belongs to
+    pub fn region_of(&mut self, pixel: usize) -> usize {
+        let label = *self.labels.get(pixel);
+        self.find(label)
+    }
+
+    /// Moves one pixel to a new region of its own and returns its label
+    pub fn detach(&mut self, pixel: usize, image: &ColorImage) -> usize {
+        let label = self.regions.len();
+        self.regions.push(Region::default());
+        self.parents.push(label);
+        self.alive += 1;
+        self.reassign(pixel, label, image);
+        label
+    }
+
+    /// Moves one pixel to another region, which should be a neighbour
+    pub fn reassign(&mut self, pixel: usize, to: usize, image: &ColorImage) {
+        let from = self.find(*self.labels.get(pixel));
+        let to = self.find(to);
+        if from == to {
+            return;
+        }
+        let (width, height) = (self.labels.width as i32, self.labels.height as i32);
+        let (y, x) = ((pixel / self.labels.width) as i32, (pixel % self.labels.width) as i32);
+        let color = image.get(pixel);
+        for (dy, dx) in FORWARD.iter() {
+            for (dy, dx) in [(*dy, *dx), (-dy, -dx)].iter() {
+                let (ny, nx) = (y + dy, x + dx);
+                if ny < 0 || ny >= height || nx < 0 || nx >= width {
+                    continue;
+                }
+                let other = ny as usize * self.labels.width + nx as usize;
+                let label = self.find(*self.labels.get(other));
+                let distance = utils::pixel_distance(color, image.get(other));
+                let side = *dy == 0 || *dx == 0;
+                if label != from {
+                    self.remove_pair(from, label, pixel, distance, side);
+                }
+                if label != to {
+                    self.add_pair(to, label, pixel, other, distance, side);
+                }
+            }
+        }
+
+        for (label, sign) in [(from, -1.0), (to, 1.0)].iter() {
+            let region = &mut self.regions[*label];
+            for c in 0..3 {
+                region.color_sum[c] += sign * color[c];
+            }
+            region.dirty = true;
+        }
+        // Swap remove, so only the moved pixel's position changes
+        let position = self.positions[pixel];
+        let pixels = &mut self.regions[from].pixels;
+        pixels.swap_remove(position);
+        if let Some(moved) = pixels.get(position) {
+            self.positions[*moved] = position;
+        }
+        self.regions[from].size -= 1;
+        self.positions[pixel] = self.regions[to].pixels.len();
+        self.regions[to].size += 1;
+        self.regions[to].pixels.push(pixel);
+        self.labels.set(to, pixel);
+        if self.regions[from].size == 0 {
+            self.alive -= 1;
+        }
+    }
+
+    /// Merges the smallest region into its dominant neighbour until every
+    /// region has at least `min_size` pixels
+    pub fn merge_small(&mut self, min_size: usize) {
+        let mut heap: BinaryHeap<Reverse<(usize, usize)>> = (0..self.regions.len())
+            .filter(|label| self.is_alive(*label))
+            .map(|label| Reverse((self.regions[label].size, label)))
+            .collect();
+        while let Some(Reverse((size, label))) = heap.pop() {
+            // Skip entries of merged regions and outdated sizes
+            if !self.is_alive(label) || self.regions[label].size != size {
+                continue;
+            }
+            if size >= min_size {
+                break;
+            }
+            let neighbour = match self.get_dominant_neighbour(label) {
+                Some(neighbour) => neighbour,
+                None => break,
+            };
+            let merged = self.merge(label, neighbour);
+            heap.push(Reverse((self.regions[merged].size, merged)));
+        }
+    }
+
+    fn get_pair(&self, a: usize, b: usize) -> Pair {
+        let distance = utils::pixel_distance(
+            &self.regions[a].get_color_mean(),
+            &self.regions[b].get_color_mean(),
+        );
+        Pair { distance, a, b }
+    }
+
+    /// Average distance between the mean colours of neighbour regions
+    pub fn mean_neighbour_distance(&self) -> f64 {
+        let mut total = 0.0;
+        let mut pairs = 0;
+        for a in 0..self.regions.len() {
+            if !self.is_alive(a) {
+                continue;
+            }
+            for b in self.regions[a].neighbours.keys().filter(|b| a < **b) {
+                total += self.get_pair(a, *b).distance;
+                pairs += 1;
+            }
+        }
+        if pairs == 0 {
+            0.0
+        } else {
+            total / pairs as f64
+        }
+    }
+
+    /// Merges the neighbours with the closest mean colours until no
+    /// neighbours are closer than `threshold`
+    pub fn merge_similar(&mut self, threshold: f64) {
+        let mut heap: BinaryHeap<Pair> = BinaryHeap::new();
+        for a in 0..self.regions.len() {
+            if !self.is_alive(a) {
+                continue;
+            }
+            for b in self.regions[a].neighbours.keys().filter(|b| a < **b) {
+                heap.push(self.get_pair(a, *b));
+            }
+        }
+        while let Some(pair) = heap.pop() {
+            if pair.distance >= threshold {
+                break;
+            }
+            // Skip pairs of merged regions and outdated distances
+            if !self.is_alive(pair.a)
+                || !self.is_alive(pair.b)
+                || self.get_pair(pair.a, pair.b).distance != pair.distance
+            {
+                continue;
+            }
+            let merged = self.merge(pair.a, pair.b);
+            let neighbours: Vec<usize> = self.regions[merged].neighbours.keys().cloned().collect();
+            for neighbour in neighbours.into_iter() {
+                heap.push(self.get_pair(merged, neighbour));
+            }
+        }
+    }
+
+    // The edge value, connectivity and overall deviation are the same
+    // values as the full scans in `ImageFitness`. Both scans count every
+    // pair from each side.
+
+    pub fn get_edge_value(&self) -> f64 {
+        -2.0 * self.boundary_distance
+    }
+
+    pub fn get_connectivity(&self) -> f64 {
+        2.0 * self.boundary_pairs as f64 / 8.0
+    }
+
+    /// Only the deviation of changed regions is recalculated
+    pub fn get_overall_deviation(&mut self, image: &ColorImage) -> f64 {
+        let mut overall_deviation = 0.0;
+        for label in 0..self.regions.len() {
+            if !self.is_alive(label) {
+                continue;
+            }
+            let region = &mut self.regions[label];
+            if region.dirty {
+                region.update_deviation(image);
+            }
+            overall_deviation += region.deviation;
+        }
+        overall_deviation
+    }
+
+    /// Label matrix with the regions numbered 0..N in the order of their
+    /// first pixel, like `SegmentMatrix::clean`
+    pub fn to_segment_matrix(&mut self) -> SegmentMatrix {
+        let mut segment_matrix = SegmentMatrix::new(0, self.labels.width, self.labels.height);
+        let mut numbers = vec![usize::MAX; self.regions.len()];
+        let mut next_number = 0;
+        for index in 0..self.labels.length {
+            let label = self.find(*self.labels.get(index));
+            if numbers[label] == usize::MAX {
+                numbers[label] = next_number;
+                next_number += 1;
+            }
+            segment_matrix.matrix.set(numbers[label], index);
+        }
+        segment_matrix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorSpace;
+    use crate::genotype::{Genotype, SpanningTree};
+    use crate::simulation::{ImageFitness, Individual};
+    use image::{Rgb, RgbImage};
+
+    fn get_fitness(graph: &mut RegionGraph, image: &ColorImage) -> [f64; 3] {
+        [
+            graph.get_edge_value(),
+            graph.get_connectivity(),
+            graph.get_overall_deviation(image),
+        ]
+    }
+
+    fn assert_fitness_eq(a: &[f64; 3], b: &[f64; 3]) {
+        assert!((a[0] - b[0]).abs() < 1e-6);
+        assert!((a[1] - b[1]).abs() < 1e-9);
+        assert!((a[2] - b[2]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_region_graph() {
+        // Three vertical stripes of width 4, 1 and 5
+        let mut segment_matrix = SegmentMatrix::new(0, 10, 4);
+        for y in 0..4 {
+            for x in 0..10 {
+                let label = if x < 4 {
+                    0
+                } else if x < 5 {
+                    1
+                } else {
+                    2
+                };
+                segment_matrix.matrix.set(label, y * 10 + x);
+            }
+        }
+        let image = RgbImage::from_pixel(10, 4, Rgb([10, 20, 30]));
+        let image = ColorImage::new(&image, ColorSpace::Rgb);
+        let mut graph = RegionGraph::new(&segment_matrix, &image);
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.regions[1].size, 4);
+        assert_eq!(graph.regions[1].neighbours[&0].length, 4);
+        assert_eq!(graph.regions[1].neighbours[&0].pairs, 10);
+        assert!(!graph.regions[0].neighbours.contains_key(&2));
+        assert_eq!(graph.regions[2].get_color_mean(), [10.0, 20.0, 30.0]);
+
+        // The middle stripe is too small and joins a neighbour
+        graph.merge_small(8);
+        assert_eq!(graph.len(), 2);
+        let merged = graph.find(1);
+        let other = if merged == graph.find(0) { 2 } else { 0 };
+        assert_eq!(graph.regions[merged].neighbours[&other].length, 4);
+        assert_eq!(graph.regions[other].neighbours[&merged].length, 4);
+        let (inside, outside) = graph.regions[merged].neighbours[&other].pixels.unwrap();
+        assert_eq!(graph.find(*graph.labels.get(inside)), merged);
+        assert_eq!(graph.find(*graph.labels.get(outside)), other);
+
+        let merged_matrix = graph.to_segment_matrix();
+        assert_eq!(*merged_matrix.matrix.get(0), 0);
+        assert_eq!(*merged_matrix.matrix.get(9), 1);
+        assert_eq!(merged_matrix.get_segments().len(), 2);
+    }
+
+    #[test]
+    fn test_incremental_fitness() {
+        let mut image = RgbImage::new(30, 20);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let value = ((x * 37 + y * 91 + x * y) % 256) as u8;
+            *pixel = Rgb([value, 255 - value, (x * 8) as u8]);
+        }
+        let image = ColorImage::new(&image, ColorSpace::Lab);
+        let segment_matrix = SpanningTree::new(&image).genotype(8, 10).decode();
+        let individual = Individual::new(Genotype::from_segment_matrix(&segment_matrix));
+        let full = [
+            individual.calc_edge_value(&image),
+            individual.calc_connectivity(),
+            individual.calc_overall_deviation(&image),
+        ];
+        let mut graph = RegionGraph::new(&segment_matrix, &image);
+        assert_fitness_eq(&get_fitness(&mut graph, &image), &full);
+
+        // Merges and reassignments give the same fitness as starting over
+        for _ in 0..3 {
+            let region = graph.random_region();
+            let neighbour = graph.get_dominant_neighbour(region).unwrap();
+            graph.merge(region, neighbour);
+        }
+        for pixel in [31, 250, 599].iter() {
+            let label = graph.find(*graph.labels.get(*pixel));
+            let to = *graph.regions[label].neighbours.keys().next().unwrap();
+            graph.reassign(*pixel, to, &image);
+        }
+        let single = graph.detach(420, &image);
+        assert_eq!(graph.regions[single].pixels, vec![420]);
+        assert_eq!(graph.region_of(420), single);
+        for label in 0..graph.regions.len() {
+            for (position, pixel) in graph.regions[label].pixels.clone().into_iter().enumerate() {
+                assert_eq!(graph.positions[pixel], position);
+                assert_eq!(graph.region_of(pixel), label);
+            }
+        }
+        let incremental = get_fitness(&mut graph, &image);
+        let mut fresh = RegionGraph::new(&graph.to_segment_matrix(), &image);
+        assert_fitness_eq(&incremental, &get_fitness(&mut fresh, &image));
+    }
+}
@@ -1,7 +1,9 @@
 use image::{Rgb, RgbImage};
 
+use crate::color::{Color, ColorImage};
 use crate::config::CONFIG;
 use crate::matrix::{Matrix, Pos};
+use crate::region::RegionGraph;
 
 pub type VisitMatrix = Matrix<bool>;
 
@@ -56,58 +58,19 @@ impl Segment {
         }
     }
 
-    /// Check all neighbour pixels of the segment and find the neighbour segment
-    /// with most connections to the current segment
-    pub fn get_dominant_neighbour(
-        &self,
-        segment_matrix: &SegmentMatrix,
-        num_segments: usize,
-    ) -> usize {
-        let mut counts = vec![0; num_segments];
-        let matrix = &segment_matrix.matrix;
-
+    /// Average colour in the colour space of the image
+    pub fn get_pixel_centroid(&self, image: &ColorImage) -> Color {
+        let mut sum: Color = [0.0; 3];
         for pos in self.positions.iter() {
-            for new_pos in matrix.get_sides(&pos).into_iter() {
-                let segment_value = matrix.get_pos(&new_pos).clone();
-                if segment_value != self.number {
-                    counts[segment_value] += 1;
-                }
+            let color = image.get_pixel(pos.x as usize, pos.y as usize);
+            for c in 0..3 {
+                sum[c] += color[c];
             }
         }
-
-        if let Some((largest_index, _value)) =
-            counts.iter().enumerate().max_by(|(_, a), (_, b)| a.cmp(&b))
-        {
-            largest_index
-        } else {
-            panic!("Could not find largest neighbour");
-        }
-    }
-
-    /// Merge the other segment into self
-    pub fn merge_in(&mut self, other: &Segment) {
-        self.positions.extend(other.positions.iter().cloned());
-        self.size = self.positions.len();
-    }
-
-    pub fn get_pixel_centroid(&self, image: &RgbImage) -> Rgb<u8> {
-        // Calcute average color for each segment
-        let mut r: f64 = 0.0;
-        let mut g: f64 = 0.0;
-        let mut b: f64 = 0.0;
-        for pos in self.positions.iter() {
-            let rgb = image.get_pixel(pos.x as u32, pos.y as u32);
-            r += rgb[0] as f64;
-            g += rgb[1] as f64;
-            b += rgb[2] as f64;
-        }
         let pixels = self.size as f64;
-        let centroid_pixel = Rgb([
-            (r / pixels).round() as u8,
-            (g / pixels).round() as u8,
-            (b / pixels).round() as u8,
-        ]);
-        centroid_pixel
+        image
+            .space
+            .centroid(&[sum[0] / pixels, sum[1] / pixels, sum[2] / pixels])
     }
 }
 
@@ -127,55 +90,6 @@ impl SegmentContainer {
     pub fn get(&self, index: usize) -> &Segment {
         &self.segments[index]
     }
-
-    fn get_smallest_index(&self) -> usize {
-        if let Some((index, _segment)) = self
-            .segments
-            .iter()
-            .enumerate()
-            .min_by(|(_, a), (_, b)| a.size.cmp(&b.size))
-        {
-            index
-        } else {
-            panic!("Could not find smallest");
-        }
-    }
-
-    fn get_segment_index(&self, number: usize) -> usize {
-        if let Some((index, _segment)) = self
-            .segments
-            .iter()
-            .enumerate()
-            .find(|(_, segment)| segment.number == number)
-        {
-            index
-        } else {
-            panic!("Could not find segment with number: {}", number);
-        }
-    }
-
-    /// Find the smallest segment and merge it with its
-    /// most connecting neighbour segment
-    pub fn merge_smallest(
-        &mut self,
-        segment_matrix: &SegmentMatrix,
-        highest_value: usize,
-    ) -> Option<Segment> {
-        // Merges smallest into neighbour and returns neighbour
-        let smallest_index = self.get_smallest_index();
-        let smallest = self.segments[smallest_index].clone();
-        if smallest.size < CONFIG.min_seg_size {
-            let neighour_number = smallest.get_dominant_neighbour(segment_matrix, highest_value);
-            let neighbour_index = self.get_segment_index(neighour_number);
-            let neighbour = &mut self.segments[neighbour_index];
-            neighbour.merge_in(&smallest);
-            let new_segment = neighbour.clone();
-            self.segments.remove(smallest_index);
-            Some(new_segment)
-        } else {
-            None
-        }
-    }
 }
 
 impl VisitMatrix {
@@ -245,40 +159,23 @@ impl SegmentMatrix {
         SegmentContainer::new_from_vec(segments)
     }
 
-    pub fn merge_all(&mut self) {
-        self.clean();
-        let mut segments = self.get_segments();
-
-        let highest_value = segments.len();
-        loop {
-            match segments.merge_smallest(&self, highest_value) {
-                Some(new_segment) => {
-                    for pos in new_segment.positions.iter() {
-                        self.matrix.set_at_pos(new_segment.number, pos);
-                    }
-                }
-                None => {
-                    break;
-                }
-            };
-        }
-        self.clean();
-    }
-
-    pub fn merge(&mut self, seg_one: &Segment, seg_two: &Segment) {
-        let mut new_segment = seg_one.clone();
-        new_segment.merge_in(seg_two);
+    /// Merges every segment smaller than MIN_SEG_SIZE into the neighbour
+    /// it shares the longest border with
+    pub fn merge_all(&mut self, image: &ColorImage) {
         self.clean();
+        let mut graph = RegionGraph::new(self, image);
+        graph.merge_small(CONFIG.min_seg_size);
+        self.matrix = graph.to_segment_matrix().matrix;
     }
 
-    pub fn into_centroid_image(&self, image: &RgbImage) -> RgbImage {
+    pub fn into_centroid_image(&self, image: &ColorImage) -> RgbImage {
         let segments = self.get_segments();
         let matrix = &self.matrix;
 
         // Calcute average color for each segment
         let colors: Vec<Rgb<u8>> = segments
             .iter()
-            .map(|segment| segment.get_pixel_centroid(&image))
+            .map(|segment| image.space.to_rgb(&segment.get_pixel_centroid(image)))
             .collect();
 
         let mut new_image = RgbImage::new(matrix.width as u32, matrix.height as u32);
@@ -1,32 +1,38 @@
 use std::collections::HashSet;
 use std::f64;
+use std::fmt;
 
-use image::{Rgb, RgbImage};
-use rand::seq::SliceRandom;
 use rand::Rng;
 use rayon::prelude::*;
 
+use crate::color::{Color, ColorImage};
 use crate::config::CONFIG;
+use crate::genotype::Genotype;
 use crate::matrix::Pos;
+use crate::objective::Objectives;
+use crate::region::RegionGraph;
 use crate::segment::SegmentMatrix;
 use crate::utils;
 
+/// One value for each of the objectives, in their configured order
 #[derive(Clone, Debug)]
 pub struct Fitness {
-    pub edge_value: f64,
-    pub connectivity: f64,
-    pub overall_deviation: f64,
+    values: Vec<f64>,
 }
 
 pub struct Individual {
     fitness: Option<Fitness>,
-    pub segment_matrix: SegmentMatrix,
+    rank: usize,            // Index of the non-dominated front
+    crowding_distance: f64, // Larger is less crowded
+    pub genotype: Genotype,
+    pub segment_matrix: SegmentMatrix, // Decoded from the genotype
+    graph: Option<RegionGraph>,        // Fitness terms of the segments, if up to date
 }
 
 pub trait ImageFitness {
     fn calc_connectivity(&self) -> f64;
-    fn calc_overall_deviation(&self, image: &RgbImage) -> f64;
-    fn calc_edge_value(&self, image: &RgbImage) -> f64;
+    fn calc_overall_deviation(&self, image: &ColorImage) -> f64;
+    fn calc_edge_value(&self, image: &ColorImage) -> f64;
 }
 
 pub struct Population {
@@ -35,6 +41,7 @@ pub struct Population {
 
 pub struct Simulation {
     iteration: usize,
+    pub objectives: Objectives,
     pub population: Population,
     fronts: Option<Fronts>,
 }
@@ -44,60 +51,51 @@ pub struct Fronts {
 }
 
 impl Fitness {
-    pub fn new(edge_value: f64, connectivity: f64, overall_deviation: f64) -> Fitness {
-        Fitness {
-            edge_value,
-            connectivity,
-            overall_deviation,
-        }
+    pub fn new(values: Vec<f64>) -> Fitness {
+        Fitness { values }
     }
 
     pub fn set_min(&mut self, other: &Fitness) {
-        if self.edge_value >= other.edge_value {
-            self.edge_value = other.edge_value;
-        }
-        if self.connectivity >= other.connectivity {
-            self.connectivity = other.connectivity
-        }
-        if self.overall_deviation >= other.overall_deviation {
-            self.overall_deviation = other.overall_deviation
+        for (value, other) in self.values.iter_mut().zip(other.values.iter()) {
+            if *value >= *other {
+                *value = *other;
+            }
         }
     }
 
     pub fn set_max(&mut self, other: &Fitness) {
-        if self.edge_value <= other.edge_value {
-            self.edge_value = other.edge_value;
-        }
-        if self.connectivity <= other.connectivity {
-            self.connectivity = other.connectivity
-        }
-        if self.overall_deviation <= other.overall_deviation {
-            self.overall_deviation = other.overall_deviation
+        for (value, other) in self.values.iter_mut().zip(other.values.iter()) {
+            if *value <= *other {
+                *value = *other;
+            }
         }
     }
 
+    /// Pareto dominance: no worse in any objective and better in at least one
     pub fn dominates(&self, other: &Fitness) -> bool {
         let v1 = self.get_values();
         let v2 = other.get_values();
-        let mut dominate = true;
+        let mut better = false;
         for i in 0..v1.len() {
-            if v1[i] >= v2[i] {
-                dominate = false;
-                break;
+            if v1[i] > v2[i] {
+                return false;
+            }
+            if v1[i] < v2[i] {
+                better = true;
             }
         }
-        dominate
+        better
     }
 
-    fn get_values(&self) -> [f64; 3] {
-        [self.edge_value, self.connectivity, self.overall_deviation]
+    pub fn get_values(&self) -> &[f64] {
+        &self.values
     }
+}
 
-    fn get_weighted(&self) -> f64 {
-        (self.edge_value * CONFIG.ev_weight
-            + self.connectivity * CONFIG.con_weight
-            + self.overall_deviation * CONFIG.od_weight)
-            / CONFIG.total_weight()
+impl fmt::Display for Fitness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: Vec<String> = self.values.iter().map(|v| format!("{:.2}", v)).collect();
+        write!(f, "{}", values.join(", "))
     }
 }
 
@@ -120,13 +118,13 @@ impl ImageFitness for Individual {
         fitness
     }
 
-    fn calc_overall_deviation(&self, image: &RgbImage) -> f64 {
+    fn calc_overall_deviation(&self, image: &ColorImage) -> f64 {
         let mut total_fitness: f64 = 0.0;
         for segment in self.segment_matrix.get_segments().iter() {
             let mut segment_fitness: f64 = 0.0;
-            let centroid_pixel: Rgb<u8> = segment.get_pixel_centroid(image);
+            let centroid_pixel: Color = segment.get_pixel_centroid(image);
             for pos in segment.positions.iter() {
-                let pixel: &Rgb<u8> = image.get_pixel(pos.x as u32, pos.y as u32);
+                let pixel: &Color = image.get_pixel(pos.x as usize, pos.y as usize);
                 segment_fitness += utils::pixel_distance(&centroid_pixel, pixel);
             }
             total_fitness += segment_fitness;
@@ -134,19 +132,19 @@ impl ImageFitness for Individual {
         total_fitness
     }
 
-    fn calc_edge_value(&self, image: &RgbImage) -> f64 {
+    fn calc_edge_value(&self, image: &ColorImage) -> f64 {
         let matrix = &self.segment_matrix.matrix;
         let mut fitness: f64 = 0.0;
         for y in 0..matrix.height {
             for x in 0..matrix.width {
                 let pos = Pos::new_usize(y, x);
                 let segment_number = matrix.get_pos(&pos);
-                let pixel: &Rgb<u8> = image.get_pixel(pos.x as u32, pos.y as u32);
+                let pixel: &Color = image.get_pixel(pos.x as usize, pos.y as usize);
                 for neighbour_pos in matrix.get_neighbours(&pos).iter() {
                     let neighour_number = matrix.get_pos(neighbour_pos);
                     if segment_number != neighour_number {
                         let neighbour_pixel =
-                            image.get_pixel(neighbour_pos.x as u32, neighbour_pos.y as u32);
+                            image.get_pixel(neighbour_pos.x as usize, neighbour_pos.y as usize);
                         fitness += utils::pixel_distance(pixel, neighbour_pixel);
                     }
                 }
@@ -158,32 +156,58 @@ impl ImageFitness for Individual {
 
 impl Clone for Individual {
     fn clone(&self) -> Individual {
-        Individual::new(self.segment_matrix.clone())
+        Individual {
+            fitness: None,
+            rank: 0,
+            crowding_distance: 0.0,
+            genotype: self.genotype.clone(),
+            segment_matrix: self.segment_matrix.clone(),
+            graph: None,
+        }
     }
 }
 
 impl Individual {
-    pub fn new(segment_matrix: SegmentMatrix) -> Individual {
+    pub fn new(genotype: Genotype) -> Individual {
         Individual {
             fitness: None,
-            segment_matrix: segment_matrix,
+            rank: 0,
+            crowding_distance: 0.0,
+            segment_matrix: genotype.decode(),
+            genotype: genotype,
+            graph: None,
         }
     }
 
+    /// Copy with the fitness, but without the region graph
     pub fn clone_with_fitness(&self) -> Individual {
         Individual {
+            genotype: self.genotype.clone(),
             segment_matrix: self.segment_matrix.clone(),
             fitness: self.fitness.clone(),
+            graph: None,
+            rank: self.rank,
+            crowding_distance: self.crowding_distance,
         }
     }
 
-    pub fn evaluate(&mut self, image: &RgbImage) {
-        let fitness = Fitness {
-            connectivity: self.calc_connectivity(),
-            overall_deviation: self.calc_overall_deviation(image),
-            edge_value: self.calc_edge_value(image),
-        };
-        self.fitness = Some(fitness);
+    /// Copy with the fitness and the region graph, so mutations of the
+    /// copy can update the graph instead of building it again
+    pub fn clone_with_graph(&self) -> Individual {
+        Individual {
+            graph: self.graph.clone(),
+            ..self.clone_with_fitness()
+        }
+    }
+
+    /// Calculates the fitness from the region graph, which is only built
+    /// again if a mutation changed the segments in a way it could not follow
+    pub fn evaluate(&mut self, image: &ColorImage, objectives: &Objectives) {
+        if self.graph.is_none() {
+            self.graph = Some(RegionGraph::new(&self.segment_matrix, image));
+        }
+        let graph = self.graph.as_mut().expect("Graph is built");
+        self.fitness = Some(objectives.evaluate(graph, image));
     }
 
     pub fn get_fitness(&self) -> &Fitness {
@@ -196,46 +220,166 @@ impl Individual {
     }
 
     pub fn crossover(&self, other: &Individual) -> Individual {
-        let mut new_individual = self.clone();
-        let other_segments = other.segment_matrix.get_segments();
+        Individual::new(self.genotype.uniform_crossover(&other.genotype))
+    }
 
-        let num_seg = std::cmp::min(CONFIG.crossover_seg_max, other_segments.len());
-        let indices: HashSet<usize> = if num_seg == other_segments.len() {
-            (0..other_segments.len()).into_iter().collect()
-        } else {
-            let mut rng = rand::thread_rng();
-            let mut set = HashSet::new();
-            while set.len() < num_seg {
-                set.insert(rng.gen_range(0, other_segments.len()));
-            }
-            set
+    /// Either links a random pixel to a new neighbour, or merges a random
+    /// segment into its dominant neighbour by joining two pixels on their
+    /// border. Both update the region graph, so only the changed segments
+    /// are evaluated again. Removing a link can split a segment, so then the
+    /// genotype is decoded again.
+    pub fn mutate(&mut self, image: &ColorImage, num_mutations: usize) {
+        self.mutate_with_rate(image, num_mutations, CONFIG.merge_mutation_rate);
+    }
+
+    fn mutate_with_rate(&mut self, image: &ColorImage, num_mutations: usize, merge_rate: f64) {
+        let mut graph = match self.graph.take() {
+            Some(graph) => graph,
+            None => RegionGraph::new(&self.segment_matrix, image),
         };
+        let mut graph_valid = true;
+        let mut rng = rand::thread_rng();
+        for _ in 0..num_mutations {
+            let merge: f64 = rng.gen();
+            let border = if merge < merge_rate && graph.len() > 1 {
+                let region = graph.random_region();
+                graph.get_dominant_neighbour(region).and_then(|neighbour| {
+                    graph.regions[region].neighbours[&neighbour]
+                        .pixels
+                        .map(|pixels| (region, neighbour, pixels))
+                })
+            } else {
+                None
+            };
+            match border {
+                Some((region, neighbour, (from, to))) => {
+                    self.genotype.join(from, to);
+                    graph.merge(region, neighbour);
+                }
+                None => {
+                    let (pixel, old) = self.genotype.mutate();
+                    if graph_valid {
+                        graph_valid = follow_link(&self.genotype, &mut graph, pixel, old, image);
+                    }
+                }
+            }
+        }
+        if graph_valid {
+            self.segment_matrix = graph.to_segment_matrix();
+            self.graph = Some(graph);
+        } else {
+            self.segment_matrix = self.genotype.decode();
+        }
+        self.fitness = None;
+    }
 
-        for i in indices.iter() {
-            let segment = other_segments.get(*i);
-            for pos in segment.positions.iter() {
-                new_individual
-                    .segment_matrix
-                    .matrix
-                    .set_at_pos(segment.number, pos);
+    /// Crowded-comparison operator: lower rank wins, then larger crowding distance
+    pub fn crowded_better(&self, other: &Individual) -> bool {
+        self.rank < other.rank
+            || (self.rank == other.rank && self.crowding_distance > other.crowding_distance)
+    }
+}
+
+/// Updates the region graph after the link of a pixel changed from `old`.
+/// Returns false if the old link may have been the only path between two
+/// parts of a segment, since the graph cannot split a region.
+fn follow_link(
+    genotype: &Genotype,
+    graph: &mut RegionGraph,
+    pixel: usize,
+    old: usize,
+    image: &ColorImage,
+) -> bool {
+    let new = *genotype.links.get(pixel);
+    if new == old {
+        return true;
+    }
+    let region = graph.region_of(pixel);
+    let other = graph.region_of(new);
+    // Nothing was cut if the old link was to itself or the old pixel also
+    // links back, so the new link can only join two segments
+    if old == pixel || *genotype.links.get(old) == pixel {
+        if region != other {
+            graph.merge(region, other);
+        }
+        return true;
+    }
+    // The old link was the only one of a pixel no other pixel links to, so
+    // the pixel leaves its segment and the rest stays connected
+    if genotype.is_linked_to(pixel) {
+        return false;
+    }
+    if new == pixel {
+        graph.detach(pixel, image);
+    } else if region != other {
+        graph.reassign(pixel, other, image);
+    }
+    true
+}
+
+/// Deb's fast non-dominated sort. Returns the indices of each front.
+pub fn fast_non_dominated_sort(fitness: &[&Fitness]) -> Vec<Vec<usize>> {
+    let size = fitness.len();
+    let mut dominated_by: Vec<Vec<usize>> = vec![Vec::new(); size]; // Solutions i dominates
+    let mut domination_count: Vec<usize> = vec![0; size]; // Solutions dominating i
+    let mut fronts: Vec<Vec<usize>> = vec![Vec::new()];
+    for i in 0..size {
+        for j in (i + 1)..size {
+            if fitness[i].dominates(fitness[j]) {
+                dominated_by[i].push(j);
+                domination_count[j] += 1;
+            } else if fitness[j].dominates(fitness[i]) {
+                dominated_by[j].push(i);
+                domination_count[i] += 1;
             }
         }
-        new_individual.segment_matrix.clean();
-        new_individual
+        // i has now been compared with every other solution
+        if domination_count[i] == 0 {
+            fronts[0].push(i);
+        }
     }
 
-    pub fn mutate(&mut self) {
-        let segments = self.segment_matrix.get_segments();
-        let mut rng = rand::thread_rng();
-        let segment = segments.get(rng.gen_range(0, segments.len()));
-        let neighbour_index = segment.get_dominant_neighbour(&self.segment_matrix, segments.len());
-        let other_segment = segments.get(neighbour_index);
-        self.segment_matrix.merge(&segment, &other_segment);
+    let mut current = 0;
+    while !fronts[current].is_empty() {
+        let mut next_front: Vec<usize> = Vec::new();
+        for i in fronts[current].iter() {
+            for j in dominated_by[*i].iter() {
+                domination_count[*j] -= 1;
+                if domination_count[*j] == 0 {
+                    next_front.push(*j);
+                }
+            }
+        }
+        fronts.push(next_front);
+        current += 1;
     }
+    fronts.pop();
+    fronts
+}
 
-    pub fn dominates(&self, other: &Individual) -> bool {
-        self.get_fitness().dominates(other.get_fitness())
+/// Crowding distance of each solution in the front, in the same order as the front
+pub fn crowding_distances(fitness: &[&Fitness], front: &[usize]) -> Vec<f64> {
+    let size = front.len();
+    let mut distances = vec![0.0; size];
+    if size <= 2 {
+        return vec![f64::INFINITY; size];
     }
+    let mut order: Vec<usize> = (0..size).collect();
+    for objective in 0..fitness[front[0]].get_values().len() {
+        let value = |i: usize| fitness[front[i]].get_values()[objective];
+        order.sort_by(|a, b| value(*a).partial_cmp(&value(*b)).unwrap());
+        let min = value(order[0]);
+        let max = value(order[size - 1]);
+        distances[order[0]] = f64::INFINITY;
+        distances[order[size - 1]] = f64::INFINITY;
+        if max - min <= 0.0 {
+            continue;
+        }
+        for k in 1..(size - 1) {
+            distances[order[k]] += (value(order[k + 1]) - value(order[k - 1])) / (max - min);
+        }
+    }
+    distances
 }
 
 impl Population {
@@ -249,100 +393,98 @@ impl Population {
         self.individuals.push(individual);
     }
 
-    pub fn evaluate(&mut self, image: &RgbImage) {
+    pub fn evaluate(&mut self, image: &ColorImage, objectives: &Objectives) {
         for (i, individual) in self.individuals.iter_mut().enumerate() {
-            individual.evaluate(image);
-            let fitness = individual.get_fitness();
-            println!(
-                "Individual: {} Fitness: {:.2}, {:.2}, {:.2}",
-                i, fitness.edge_value, fitness.connectivity, fitness.overall_deviation
-            );
+            individual.evaluate(image, objectives);
+            println!("Individual: {} Fitness: {}", i, individual.get_fitness());
         }
     }
 
-    pub fn get_fronts(&self) -> Fronts {
-        let pop_size = self.individuals.len();
-        let mut choosed: Vec<bool> = vec![false; self.individuals.len()];
-        let mut fronts: Vec<Vec<Individual>> = Vec::new();
-        let mut count = 0;
-        while count < pop_size {
-            let mut front_indices: Vec<usize> = Vec::new();
-            for i in 0..pop_size {
-                if choosed[i] {
-                    continue;
-                }
-                let individual = &self.individuals[i];
-                let mut dominated = false;
-                for j in 0..pop_size {
-                    if i == j || choosed[j] {
-                        continue;
-                    }
-                    let other = &self.individuals[j];
-                    if other.dominates(individual) {
-                        dominated = true;
-                        break;
-                    }
-                }
-                if !dominated {
-                    front_indices.push(i);
-                }
+    /// Sets the rank and crowding distance of every individual and
+    /// returns the indices of each front
+    pub fn assign_rank_and_crowding(&mut self) -> Vec<Vec<usize>> {
+        let fitness: Vec<&Fitness> = self.individuals.iter().map(|i| i.get_fitness()).collect();
+        let fronts = fast_non_dominated_sort(&fitness);
+        let distances: Vec<Vec<f64>> = fronts
+            .iter()
+            .map(|front| crowding_distances(&fitness, front))
+            .collect();
+        for (rank, (front, front_distances)) in fronts.iter().zip(distances.iter()).enumerate() {
+            for (index, distance) in front.iter().zip(front_distances.iter()) {
+                self.individuals[*index].rank = rank;
+                self.individuals[*index].crowding_distance = *distance;
             }
-            let mut front: Vec<Individual> = front_indices
-                .into_iter()
-                .map(|index| {
-                    count += 1;
-                    choosed[index] = true;
-                    self.individuals[index].clone_with_fitness()
-                })
-                .collect();
-            front.sort_by(|a, b| {
-                a.get_fitness()
-                    .connectivity
-                    .partial_cmp(&b.get_fitness().connectivity)
-                    .unwrap()
-            });
-            fronts.push(front);
         }
-
-        Fronts { layers: fronts }
+        fronts
     }
 
-    pub fn print_fronts(&self, fronts: &Fronts) {
-        for (i, f) in fronts.layers.iter().enumerate() {
-            println!("Front: {}", i);
-            for ind in f.iter() {
-                let fitness = ind.get_fitness();
-                println!(
-                    "Fitness: {:.2}, {:.2}, {:.2}, weighted: {:.2}",
-                    fitness.edge_value,
-                    fitness.connectivity,
-                    fitness.overall_deviation,
-                    fitness.get_weighted()
-                );
-            }
-            println!("");
-        }
+    pub fn get_fronts(&self) -> Fronts {
+        let fitness: Vec<&Fitness> = self.individuals.iter().map(|i| i.get_fitness()).collect();
+        let fronts = fast_non_dominated_sort(&fitness)
+            .into_iter()
+            .enumerate()
+            .map(|(rank, front)| {
+                let distances = crowding_distances(&fitness, &front);
+                let mut layer: Vec<Individual> = front
+                    .iter()
+                    .zip(distances.into_iter())
+                    .map(|(index, distance)| {
+                        let mut individual = self.individuals[*index].clone_with_fitness();
+                        individual.rank = rank;
+                        individual.crowding_distance = distance;
+                        individual
+                    })
+                    .collect();
+                // Least crowded first, so the boundary solutions come first
+                layer.sort_by(|a, b| {
+                    b.crowding_distance
+                        .partial_cmp(&a.crowding_distance)
+                        .unwrap()
+                });
+                layer
+            })
+            .collect();
+
+        Fronts { layers: fronts }
     }
 }
 
 impl Simulation {
-    pub fn new() -> Simulation {
+    pub fn new(objectives: Objectives) -> Simulation {
         Simulation {
+            objectives,
             population: Population::new(),
             iteration: 0,
             fronts: None,
         }
     }
-    pub fn add_initial(&mut self, segment_matrices: Vec<SegmentMatrix>) {
-        let num_segments = segment_matrices.len();
+    pub fn add_initial(&mut self, genotypes: Vec<Genotype>) {
+        let num_genotypes = genotypes.len();
         for i in 0..CONFIG.population_size {
-            let segment_matrix = segment_matrices[i % num_segments].clone();
-            self.population.add(Individual::new(segment_matrix));
+            let genotype = genotypes[i % num_genotypes].clone();
+            self.population.add(Individual::new(genotype));
         }
     }
 
-    pub fn evaluate(&mut self, image: &RgbImage) {
-        self.population.evaluate(image);
+    pub fn evaluate(&mut self, image: &ColorImage) {
+        self.population.evaluate(image, &self.objectives);
+        self.population.assign_rank_and_crowding();
+    }
+
+    pub fn print_fronts(&self, fronts: &Fronts) {
+        println!("Objectives: {}", self.objectives.names().join(", "));
+        for (i, f) in fronts.layers.iter().enumerate() {
+            println!("Front: {}", i);
+            for ind in f.iter() {
+                let fitness = ind.get_fitness();
+                println!(
+                    "Fitness: {}, weighted: {:.2}",
+                    fitness,
+                    self.objectives.get_weighted(fitness)
+                );
+            }
+            println!("");
+        }
     }
 
     pub fn tournament(&self) -> usize {
@@ -356,19 +498,38 @@ impl Simulation {
         let mut best_score = f64::MAX;
         for num in numbers.iter() {
             let ind = &self.population.individuals[*num];
-            if ind.get_fitness().get_weighted() < best_score {
+            let score = self.objectives.get_weighted(ind.get_fitness());
+            if score < best_score {
                 best = Some(*num);
-                best_score = ind.get_fitness().get_weighted();
+                best_score = score;
             }
         }
 
         best.unwrap()
     }
 
-    pub fn evolve(&mut self, image: &RgbImage) {
-        let fronts = &self.population.get_fronts();
-        let first_layer = &fronts.layers[0];
-        let first_size = first_layer.len();
+    /// Tournament on rank and crowding distance
+    pub fn crowded_tournament(&self) -> usize {
+        let mut numbers: HashSet<usize> = HashSet::new();
+        let mut rng = rand::thread_rng();
+        let k = std::cmp::min(CONFIG.tournament_k, self.population.individuals.len());
+        while numbers.len() < k {
+            numbers.insert(rng.gen_range(0, self.population.individuals.len()));
+        }
+
+        let mut best: Option<usize> = None;
+        for num in numbers.iter() {
+            let ind = &self.population.individuals[*num];
+            best = match best {
+                Some(b) if !ind.crowded_better(&self.population.individuals[b]) => Some(b),
+                _ => Some(*num),
+            };
+        }
+
+        best.unwrap()
+    }
+
+    pub fn evolve(&mut self, image: &ColorImage) {
         let mut new_individuals: Vec<Individual> = Vec::with_capacity(CONFIG.population_size * 2);
         let mut rng = rand::thread_rng();
         if CONFIG.weighted {
@@ -380,65 +541,70 @@ impl Simulation {
                 let (mut c1, mut c2) = if cross < CONFIG.crossover_rate {
                     (p1.crossover(p2), p2.crossover(p1))
                 } else {
-                    (p1.clone_with_fitness(), p2.clone_with_fitness())
+                    (p1.clone_with_graph(), p2.clone_with_graph())
                 };
                 if mutate < CONFIG.mutation_rate {
                     let num_mutations = rng.gen_range(0, CONFIG.mutations_max);
-                    for _ in 0..num_mutations {
-                        c1.mutate();
-                        c2.mutate();
-                    }
+                    c1.mutate(image, num_mutations);
+                    c2.mutate(image, num_mutations);
                 }
                 new_individuals.push(c1);
                 new_individuals.push(c2);
             }
+            let objectives = &self.objectives;
             new_individuals.par_iter_mut().for_each(|ind| {
-                ind.evaluate(image);
+                ind.evaluate(image, objectives);
             });
             self.population.individuals = new_individuals;
         } else {
             while new_individuals.len() < CONFIG.population_size {
-                let p1 = &first_layer[rng.gen_range(0, first_size)];
-                let p2 = &first_layer[rng.gen_range(0, first_size)];
+                let p1 = &self.population.individuals[self.crowded_tournament()];
+                let p2 = &self.population.individuals[self.crowded_tournament()];
                 let cross: f64 = rng.gen();
                 let mutate: f64 = rng.gen();
                 let (mut c1, mut c2) = if cross < CONFIG.crossover_rate {
                     (p1.crossover(p2), p2.crossover(p1))
                 } else {
-                    (p1.clone_with_fitness(), p2.clone_with_fitness())
+                    (p1.clone_with_graph(), p2.clone_with_graph())
                 };
                 if mutate < CONFIG.mutation_rate {
                     let num_mutations = rng.gen_range(0, CONFIG.mutations_max);
-                    for _ in 0..num_mutations {
-                        c1.mutate();
-                        c2.mutate();
-                    }
+                    c1.mutate(image, num_mutations);
+                    c2.mutate(image, num_mutations);
                 }
                 new_individuals.push(c1);
                 new_individuals.push(c2);
             }
+            let objectives = &self.objectives;
             new_individuals.par_iter_mut().for_each(|ind| {
-                ind.evaluate(image);
+                ind.evaluate(image, objectives);
             });
-            for old in self.population.individuals.iter() {
-                new_individuals.push(old.clone_with_fitness());
-            }
+            // The old individuals are moved, so their graphs are not copied
+            new_individuals.extend(std::mem::take(&mut self.population.individuals));
 
             let mut new_pop = Population {
                 individuals: new_individuals,
             };
 
-            let new_fronts = new_pop.get_fronts();
-            let mut new_ind = Vec::new();
-            for mut layer in new_fronts.layers {
-                let mut rng = rand::thread_rng();
-                layer.shuffle(&mut rng);
-                for ind in layer {
-                    if new_ind.len() < CONFIG.population_size {
-                        new_ind.push(ind);
-                    } else {
-                        break;
-                    }
+            // Fill with whole fronts, then the least crowded of the last front
+            let new_fronts = new_pop.assign_rank_and_crowding();
+            let mut candidates: Vec<Option<Individual>> =
+                new_pop.individuals.into_iter().map(Some).collect();
+            let mut new_ind = Vec::with_capacity(CONFIG.population_size);
+            for mut layer in new_fronts {
+                if new_ind.len() >= CONFIG.population_size {
+                    break;
+                }
+                if new_ind.len() + layer.len() > CONFIG.population_size {
+                    layer.sort_by(|a, b| {
+                        let a = candidates[*a].as_ref().unwrap().crowding_distance;
+                        let b = candidates[*b].as_ref().unwrap().crowding_distance;
+                        b.partial_cmp(&a).unwrap()
+                    });
+                    layer.truncate(CONFIG.population_size - new_ind.len());
+                }
+                for index in layer {
+                    new_ind.push(candidates[index].take().unwrap());
                 }
             }
             self.population.individuals = new_ind;
@@ -448,8 +614,14 @@ impl Simulation {
 
 impl Fronts {
     pub fn get_ranges(&self) -> (Fitness, Fitness) {
-        let mut min = Fitness::new(f64::MAX, f64::MAX, f64::MAX);
-        let mut max = Fitness::new(f64::MIN, f64::MIN, f64::MIN);
+        let objectives = self
+            .layers
+            .iter()
+            .flat_map(|layer| layer.iter())
+            .next()
+            .map_or(0, |individual| individual.get_fitness().get_values().len());
+        let mut min = Fitness::new(vec![f64::MAX; objectives]);
+        let mut max = Fitness::new(vec![f64::MIN; objectives]);
         for layer in self.layers.iter() {
             for individual in layer.iter() {
                 min.set_min(&individual.get_fitness());
@@ -469,13 +641,20 @@ impl Fronts {
         for layer in self.layers.iter() {
             let mut layer_vec = Vec::new();
             for individual in layer.iter() {
-                let f = individual.get_fitness();
-                let ev = (f.edge_value - min_f.edge_value) / (max_f.edge_value - min_f.edge_value);
-                let con = (f.connectivity - min_f.connectivity)
-                    / (max_f.connectivity - min_f.connectivity);
-                let od = (f.overall_deviation - min_f.overall_deviation)
-                    / (max_f.overall_deviation - min_f.overall_deviation);
-                let new_fit = Fitness::new(ev, con, od);
+                let values = individual
+                    .get_fitness()
+                    .get_values()
+                    .iter()
+                    .zip(min_f.get_values().iter().zip(max_f.get_values().iter()))
+                    .map(|(value, (min, max))| {
+                        if max > min {
+                            (value - min) / (max - min)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect();
+                let new_fit = Fitness::new(values);
                 layer_vec.push((ind_num, new_fit));
                 ind_num += 1;
             }
@@ -488,12 +667,72 @@ impl Fronts {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::color::ColorSpace;
+    use crate::genotype::SpanningTree;
+    use image::{Rgb, RgbImage};
+
+    fn test_image() -> ColorImage {
+        let mut image = RgbImage::new(30, 20);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let value = ((x * 37 + y * 91 + x * y) % 256) as u8;
+            *pixel = Rgb([value, 255 - value, (x * 8) as u8]);
+        }
+        ColorImage::new(&image, ColorSpace::Rgb)
+    }
+
+    #[test]
+    fn test_merge_mutation() {
+        let image = test_image();
+        let tree = SpanningTree::new(&image);
+        for _ in 0..10 {
+            let mut individual = Individual::new(tree.genotype(8, 10));
+            let segments = individual.segment_matrix.get_segments().len();
+            individual.mutate_with_rate(&image, 3, 1.0);
+            assert_eq!(individual.segment_matrix.get_segments().len(), segments - 3);
+            assert_eq!(
+                individual.genotype.decode().get_segments().len(),
+                segments - 3
+            );
+        }
+    }
+
+    #[test]
+    fn test_mutation_matches_genotype() {
+        let image = test_image();
+        let tree = SpanningTree::new(&image);
+        for rate in [1.0, 0.5, 0.0].iter() {
+            let mut kept = 0;
+            for i in 0..60 {
+                let mut individual = Individual::new(tree.genotype(8, 10));
+                individual.mutate_with_rate(&image, 1 + i % 3, *rate);
+                let decoded = individual.genotype.decode();
+                for index in 0..decoded.matrix.length {
+                    assert_eq!(
+                        decoded.matrix.get(index),
+                        individual.segment_matrix.matrix.get(index)
+                    );
+                }
+
+                // A graph that followed the mutations has the same fitness terms
+                if let Some(graph) = individual.graph.as_mut() {
+                    kept += 1;
+                    let mut fresh = RegionGraph::new(&decoded, &image);
+                    assert!((graph.get_edge_value() - fresh.get_edge_value()).abs() < 1e-6);
+                    assert_eq!(graph.get_connectivity(), fresh.get_connectivity());
+                    let deviation = graph.get_overall_deviation(&image);
+                    assert!((deviation - fresh.get_overall_deviation(&image)).abs() < 1e-6);
+                }
+            }
+            // Link mutations only rebuild the graph when a link may split a segment
+            assert!(kept > 0);
+        }
+    }
 
     #[test]
     fn test_fitness_domination() {
-        let f1 = Fitness::new(10.0, 10.0, 10.0);
-        let f2 = Fitness::new(11.0, 10.0, 9.0);
-        let f3 = Fitness::new(9.5, 9.5, 9.5);
+        let f1 = Fitness::new(vec![10.0, 10.0, 10.0]);
+        let f2 = Fitness::new(vec![11.0, 10.0, 9.0]);
+        let f3 = Fitness::new(vec![9.5, 9.5, 9.5]);
 
         // f1 and f2 no domination
         assert_eq!(f1.dominates(&f2), false);
@@ -506,5 +745,53 @@ mod tests {
         // f2 and f3 no domination
         assert_eq!(f2.dominates(&f3), false);
         assert_eq!(f3.dominates(&f2), false);
+
+        // Ties count as long as one objective is better, equal fitness does not dominate
+        let f4 = Fitness::new(vec![10.0, 9.0, 10.0]);
+        assert_eq!(f4.dominates(&f1), true);
+        assert_eq!(f1.dominates(&f4), false);
+        assert_eq!(f1.dominates(&f1.clone()), false);
+    }
+
+    #[test]
+    fn test_fast_non_dominated_sort() {
+        let fitness = vec![
+            Fitness::new(vec![3.0, 3.0, 3.0]),
+            Fitness::new(vec![1.0, 1.0, 1.0]),
+            Fitness::new(vec![2.0, 2.0, 2.0]),
+            Fitness::new(vec![0.5, 4.0, 4.0]),
+            Fitness::new(vec![2.5, 2.5, 0.5]),
+            Fitness::new(vec![1.0, 1.0, 2.0]),
+        ];
+        let refs: Vec<&Fitness> = fitness.iter().collect();
+        let mut fronts = fast_non_dominated_sort(&refs);
+        for front in fronts.iter_mut() {
+            front.sort();
+        }
+
+        // 1 dominates 5 with a tie in two objectives, 5 dominates 2 and 0,
+        // 2 dominates 0, 3 and 4 dominate nothing
+        assert_eq!(fronts, vec![vec![1, 3, 4], vec![5], vec![2], vec![0]]);
+    }
+
+    #[test]
+    fn test_crowding_distance() {
+        let fitness = vec![
+            Fitness::new(vec![1.0, 5.0, 0.0]),
+            Fitness::new(vec![4.0, 1.0, 2.0]),
+            Fitness::new(vec![2.0, 3.0, 1.0]),
+            Fitness::new(vec![3.0, 2.0, 1.5]),
+        ];
+        let refs: Vec<&Fitness> = fitness.iter().collect();
+        let distances = crowding_distances(&refs, &[0, 1, 2, 3]);
+
+        // The boundary solutions are infinitely far away
+        assert_eq!(distances[0], f64::INFINITY);
+        assert_eq!(distances[1], f64::INFINITY);
+        // Neighbour gaps divided by the range of each objective
+        assert!((distances[2] - (2.0 / 3.0 + 3.0 / 4.0 + 1.5 / 2.0)).abs() < 1e-12);
+        assert!((distances[3] - (2.0 / 3.0 + 2.0 / 4.0 + 1.0 / 2.0)).abs() < 1e-12);
+
+        assert_eq!(crowding_distances(&refs, &[2, 3]), vec![f64::INFINITY; 2]);
     }
 }
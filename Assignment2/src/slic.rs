@@ -0,0 +1,184 @@
+use crate::color::{Color, ColorImage};
+use crate::config::CONFIG;
+use crate::region::RegionGraph;
+use crate::segment::SegmentMatrix;
+use crate::utils;
+
+struct Center {
+    color: Color,
+    x: f64,
+    y: f64,
+}
+
+/// Squared colour change around a pixel, which must not be on the border
+fn gradient(image: &ColorImage, x: usize, y: usize) -> f64 {
+    utils::pixel_distance(image.get_pixel(x + 1, y), image.get_pixel(x - 1, y)).powi(2)
+        + utils::pixel_distance(image.get_pixel(x, y + 1), image.get_pixel(x, y - 1)).powi(2)
+}
+
+/// Center in the 3x3 window around the pixel with the lowest gradient,
+/// so centers do not start on an edge
+fn get_center(image: &ColorImage, x: usize, y: usize) -> Center {
+    let (mut best_x, mut best_y) = (x, y);
+    let mut best_gradient = f64::MAX;
+    for ny in y.saturating_sub(1).max(1)..(y + 2).min(image.height - 1) {
+        for nx in x.saturating_sub(1).max(1)..(x + 2).min(image.width - 1) {
+            let gradient = gradient(image, nx, ny);
+            if gradient < best_gradient {
+                best_gradient = gradient;
+                best_x = nx;
+                best_y = ny;
+            }
+        }
+    }
+    Center {
+        color: *image.get_pixel(best_x, best_y),
+        x: best_x as f64,
+        y: best_y as f64,
+    }
+}
+
+/// SLIC superpixels: k-means on colour and position, where every center
+/// only looks at the pixels within twice the grid step. Disconnected
+/// pieces smaller than a quarter of a superpixel join a neighbour.
+pub fn slic(
+    image: &ColorImage,
+    superpixels: usize,
+    compactness: f64,
+    iterations: usize,
+) -> SegmentMatrix {
+    let (width, height) = (image.width, image.height);
+    let step = ((width * height) as f64 / superpixels as f64)
+        .sqrt()
+        .max(1.0);
+
+    let mut centers: Vec<Center> = Vec::new();
+    let mut y = step / 2.0;
+    while y < height as f64 {
+        let mut x = step / 2.0;
+        while x < width as f64 {
+            centers.push(get_center(image, x as usize, y as usize));
+            x += step;
+        }
+        y += step;
+    }
+
+    // Spatial distance is measured in grid steps and weighted by the compactness
+    let weight = (compactness / step).powi(2);
+    let mut labels = vec![0; width * height];
+    let mut distances = vec![f64::MAX; width * height];
+    for _ in 0..iterations {
+        for distance in distances.iter_mut() {
+            *distance = f64::MAX;
+        }
+        for (label, center) in centers.iter().enumerate() {
+            let (x0, x1) = (
+                (center.x - step).max(0.0) as usize,
+                ((center.x + step) as usize + 1).min(width),
+            );
+            let (y0, y1) = (
+                (center.y - step).max(0.0) as usize,
+                ((center.y + step) as usize + 1).min(height),
+            );
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let index = y * width + x;
+                    let color_distance = utils::pixel_distance(&center.color, image.get(index));
+                    let (dx, dy) = (x as f64 - center.x, y as f64 - center.y);
+                    let distance = color_distance.powi(2) + (dx * dx + dy * dy) * weight;
+                    if distance < distances[index] {
+                        distances[index] = distance;
+                        labels[index] = label;
+                    }
+                }
+            }
+        }
+
+        // Move every center to the mean of its pixels
+        let mut sums = vec![([0.0; 3], 0.0, 0.0, 0); centers.len()];
+        for (index, label) in labels.iter().enumerate() {
+            let sum = &mut sums[*label];
+            let color = image.get(index);
+            for c in 0..3 {
+                sum.0[c] += color[c];
+            }
+            sum.1 += (index % width) as f64;
+            sum.2 += (index / width) as f64;
+            sum.3 += 1;
+        }
+        for (center, (color, x, y, count)) in centers.iter_mut().zip(sums.into_iter()) {
+            if count == 0 {
+                continue;
+            }
+            let count = count as f64;
+            center.color = [color[0] / count, color[1] / count, color[2] / count];
+            center.x = x / count;
+            center.y = y / count;
+        }
+    }
+
+    let mut segment_matrix = SegmentMatrix::new(0, width, height);
+    for (index, label) in labels.into_iter().enumerate() {
+        segment_matrix.matrix.set(label, index);
+    }
+    segment_matrix.clean();
+    let mut graph = RegionGraph::new(&segment_matrix, image);
+    graph.merge_small((step * step / 4.0) as usize);
+    graph.to_segment_matrix()
+}
+
+/// Initial segmentations from SLIC superpixels. Neighbour superpixels with
+/// closer mean colours than a threshold are merged, with `count` thresholds
+/// evenly spaced up to twice the mean distance between neighbours.
+pub fn merged_superpixels(image: &ColorImage, count: usize) -> Vec<SegmentMatrix> {
+    let superpixels = slic(
+        image,
+        CONFIG.slic_superpixels,
+        CONFIG.slic_compactness,
+        CONFIG.slic_iterations,
+    );
+    let mut graph = RegionGraph::new(&superpixels, image);
+    graph.merge_small(CONFIG.min_seg_size);
+    let mean_distance = graph.mean_neighbour_distance();
+    (0..count)
+        .map(|i| {
+            graph.merge_similar(mean_distance * 2.0 * (i + 1) as f64 / count as f64);
+            graph.to_segment_matrix()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorSpace;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_slic() {
+        // Red on the left, blue on the right of x = 25, with a bit of noise
+        let mut image = RgbImage::new(60, 30);
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let noise = ((x * 7 + y * 3) % 5) as u8;
+            *pixel = if x < 25 {
+                Rgb([200 + noise, 20, 20])
+            } else {
+                Rgb([20, 20 + noise, 200])
+            };
+        }
+        let image = ColorImage::new(&image, ColorSpace::Lab);
+        let superpixels = slic(&image, 18, 10.0, 10);
+        let segments = superpixels.get_segments();
+        assert!(segments.len() > 4);
+
+        // Superpixels follow the colour edge
+        for segment in segments.iter() {
+            let left = segment.positions.iter().filter(|pos| pos.x < 25).count();
+            assert!(left == 0 || left == segment.size);
+        }
+
+        let mut graph = RegionGraph::new(&superpixels, &image);
+        graph.merge_similar(graph.mean_neighbour_distance());
+        assert_eq!(graph.len(), 2);
+    }
+}
@@ -1,8 +1,5 @@
-use image::Rgb;
+use crate::color::Color;
 
-pub fn pixel_distance(a: &Rgb<u8>, b: &Rgb<u8>) -> f64 {
-    ((a[0] as f64 - b[0] as f64).powi(2)
-        + (a[1] as f64 - b[1] as f64).powi(2)
-        + (a[2] as f64 - b[2] as f64).powi(2))
-    .sqrt()
+pub fn pixel_distance(a: &Color, b: &Color) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
 }
@@ -0,0 +1,165 @@
+use std::fs;
+
+use rayon::prelude::*;
+
+use crate::color::ColorImage;
+use crate::config::CONFIG;
+use crate::genotype::{Genotype, SpanningTree};
+use crate::indicators::Indicators;
+use crate::objective::Objectives;
+use crate::simulation::{Fitness, Fronts, Simulation};
+
+/// Called with the fronts after every generation
+pub type FrontsCallback = Box<dyn Fn(Fronts) + Send>;
+
+pub struct Worker {
+    image_number: String,
+    image: image::RgbImage,
+    color_image: ColorImage, // The image in COLOR_SPACE
+    simulation: Simulation,
+    indicators: Option<Indicators>, // Set by `run`
+    on_fronts: FrontsCallback,
+}
+
+/// Result of segmenting one image, used for the headless summary
+pub struct ImageSummary {
+    pub image_number: String,
+    pub segments: Vec<usize>, // Number of segments of each individual in front 0
+    pub fitness: Vec<Fitness>,
+    pub images_written: usize,
+    pub seconds: f64,
+}
+
+impl Worker {
+    pub fn new(image_number: &str, on_fronts: FrontsCallback) -> Worker {
+        let image: image::RgbImage = match image::open(&CONFIG.image_path_for(image_number)) {
+            Ok(image) => image.into_rgb(),
+            Err(_) => panic!("Unable to load image {}!", image_number),
+        };
+        let color_image = ColorImage::new(&image, CONFIG.color_space());
+        let objectives = Objectives::from_config(&color_image);
+        Worker {
+            image_number: image_number.to_string(),
+            image,
+            color_image,
+            simulation: Simulation::new(objectives),
+            indicators: None,
+            on_fronts,
+        }
+    }
+
+    pub fn run(&mut self) -> Fronts {
+        let genotypes = self.initial_genotypes();
+        self.simulation.add_initial(genotypes);
+        self.simulation.evaluate(&self.color_image);
+        println!("Evaluated!");
+        let mut fronts = self.simulation.population.get_fronts();
+        self.simulation.print_fronts(&fronts);
+        let mut indicators = Indicators::from_config(&self.image_number, &fronts);
+        println!("{}", indicators.add(&fronts));
+        (self.on_fronts)(fronts);
+        for _ in 0..CONFIG.generations {
+            self.simulation.evolve(&self.color_image);
+            fronts = self.simulation.population.get_fronts();
+            self.simulation.print_fronts(&fronts);
+            println!("{}", indicators.add(&fronts));
+            (self.on_fronts)(fronts);
+        }
+        self.indicators = Some(indicators);
+
+        self.simulation.population.get_fronts()
+    }
+
+    fn initial_genotypes(&self) -> Vec<Genotype> {
+        let image = &self.color_image;
+        match CONFIG.initialization.as_str() {
+            "mst" => {
+                let tree = SpanningTree::new(image);
+                (0..CONFIG.kmeans)
+                    .into_par_iter()
+                    .map(|i| tree.genotype(i + 2, CONFIG.min_seg_size))
+                    .collect()
+            }
+            "kmeans" => (0..CONFIG.kmeans)
+                .into_par_iter()
+                .map(|i| {
+                    let segment_matrix = crate::kmeans::kmeans(image, i + 2);
+                    Genotype::from_segment_matrix(&segment_matrix)
+                })
+                .collect(),
+            "slic" => crate::slic::merged_superpixels(image, CONFIG.kmeans)
+                .iter()
+                .map(Genotype::from_segment_matrix)
+                .collect(),
+            other => panic!("Unknown initialization: {}", other),
+        }
+    }
+
+    /// Writes the green and black border images of every individual in front 0
+    /// and returns the number of individuals written. The files are named by
+    /// the segment count and the index in the front, so none are overwritten.
+    pub fn save_front(&self, fronts: &Fronts) -> usize {
+        let out_path = CONFIG.out_path_for(&self.image_number);
+        if let Err(err) = fs::create_dir_all(&out_path) {
+            panic!("Unable to create folder {}. Error: {}", out_path, err);
+        }
+
+        let mut written = 0;
+        for (index, individual) in fronts.layers[0].iter().enumerate() {
+            let segment_matrix = &individual.segment_matrix;
+            let number = segment_matrix.get_segments().len();
+            if number > CONFIG.max_segments {
+                continue;
+            }
+            let image = segment_matrix.into_green_border_image(&self.image);
+            let image_path = format!("{}/green-image-{}-{}.jpg", out_path, number, index);
+            println!("Image format: {}", image_path);
+            save_image(&image_path, &image);
+            let image = segment_matrix.into_border_image();
+            let image_path = format!("{}/border-image-{}-{}.jpg", out_path, number, index);
+            println!("Image format: {}", image_path);
+            save_image(&image_path, &image);
+            written += 1;
+        }
+        written
+    }
+
+    /// Writes the indicators of every generation to indicators.csv
+    pub fn save_indicators(&self) {
+        let indicators = match &self.indicators {
+            Some(indicators) => indicators,
+            None => panic!("No indicators before the run!"),
+        };
+        let out_path = CONFIG.out_path_for(&self.image_number);
+        if let Err(err) = fs::create_dir_all(&out_path) {
+            panic!("Unable to create folder {}. Error: {}", out_path, err);
+        }
+        indicators.save_csv(&format!("{}/indicators.csv", out_path));
+    }
+
+    pub fn summarize(&self, fronts: &Fronts, images_written: usize, seconds: f64) -> ImageSummary {
+        let best = fronts.get_best();
+        ImageSummary {
+            image_number: self.image_number.clone(),
+            segments: best
+                .iter()
+                .map(|ind| ind.segment_matrix.get_segments().len())
+                .collect(),
+            fitness: best.iter().map(|ind| ind.get_fitness().clone()).collect(),
+            images_written,
+            seconds,
+        }
+    }
+}
+
+fn save_image(image_path: &str, image: &image::RgbImage) {
+    image::save_buffer_with_format(
+        image_path,
+        image,
+        image.width(),
+        image.height(),
+        image::ColorType::Rgb8,
+        image::ImageFormat::Jpeg,
+    )
+    .expect("Unable to save image");
+}